@@ -1,7 +1,7 @@
 use std::io::Cursor;
 use std::sync::LazyLock;
 
-use clipvault::cli::{GetDelArgs, ListArgs, StoreArgs};
+use clipvault::cli::{GetArgs, GetDelArgs, ListArgs, StoreArgs};
 use clipvault::commands::{get, list, store};
 use clipvault::defaults;
 use tempfile::NamedTempFile;
@@ -46,9 +46,12 @@ fn list(n: usize) {
 fn get(n: isize) {
     let path_db = DB.path();
 
-    let args = GetDelArgs {
-        input: String::new(),
-        index: Some(n),
+    let args = GetArgs {
+        entry: GetDelArgs {
+            input: String::new(),
+            index: Some(n),
+        },
+        ..Default::default()
     };
 
     get::execute_without_output(path_db, args).expect("failed to get");
@@ -1,6 +1,7 @@
 #!/usr/bin/env bash
 # An `fzf` wrapper, with text and image previews (using sixel).
-# Usage: /path/to/clipvault_fzf.sh
+# Usage: /path/to/clipvault_fzf.sh [LIST ARGS...]
+# e.g. /path/to/clipvault_fzf.sh --type image --since 1h
 
 case "$1" in
     preview)
@@ -16,7 +17,7 @@ case "$1" in
         fi
         ;;
     *)
-        exec clipvault list \
+        exec clipvault list "$@" \
             | fzf -d $'\t' --with-nth 2 --preview "$(realpath "$0") preview {}" \
             | {
                 read -r output && clipvault get <<< "$output" | wl-copy
@@ -0,0 +1,225 @@
+//! Portable format used to export and import the history, independent of the database schema.
+//!
+//! An archive is newline-delimited JSON: a [`Header`] on the first line, followed by one
+//! [`Entry`] per line, oldest first. Content is either embedded as base64, or written to a separate
+//! file when using the directory layout, in which case the JSON lines are written to
+//! [`DIR_INDEX`] and file paths are relative to the directory.
+
+use std::path::PathBuf;
+
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::database::data::{ClipboardEntry, EntryPart, FullEntry};
+
+/// Identifies the content of a file as an archive.
+pub const FORMAT: &str = "clipvault";
+
+/// Version of the archive format, increased for changes which older versions can't read.
+pub const VERSION: u32 = 1;
+
+/// Name of the file containing the JSON lines in the directory layout.
+pub const DIR_INDEX: &str = "index.ndjson";
+
+/// First line of an archive.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Header {
+    pub format: String,
+    pub version: u32,
+}
+
+impl Default for Header {
+    fn default() -> Self {
+        Self {
+            format: FORMAT.into(),
+            version: VERSION,
+        }
+    }
+}
+
+/// Where to find the content of an entry or part.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Content {
+    /// Embedded in the JSON, encoded as base64.
+    #[serde(rename = "content", with = "base64_bytes")]
+    Inline(Vec<u8>),
+    /// Path to a file containing the content, relative to the archive directory.
+    #[serde(rename = "file")]
+    File(PathBuf),
+}
+
+/// An entry in the history, with all of its representations.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Entry {
+    /// ID in the database the entry was exported from.
+    pub id: u64,
+    /// Unix timestamp.
+    pub last_updated: u64,
+    pub mime_type: Option<String>,
+    pub pinned: bool,
+    /// Unix timestamp after which the entry is deleted. Only included if set, so that older
+    /// versions can still read the archive.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<u64>,
+    pub tags: Vec<String>,
+    #[serde(flatten)]
+    pub content: Content,
+    pub parts: Vec<Part>,
+}
+
+/// An additional representation of an entry.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Part {
+    pub mime_type: String,
+    #[serde(flatten)]
+    pub content: Content,
+}
+
+impl Entry {
+    /// Convert the entry, using the given function to decide where to put each piece of content.
+    /// The function is given the content and the index of the part, or `None` for the main
+    /// representation.
+    pub fn new<E>(
+        full: FullEntry,
+        mut content: impl FnMut(Vec<u8>, Option<usize>) -> Result<Content, E>,
+    ) -> Result<Self, E> {
+        let FullEntry {
+            entry,
+            pinned,
+            expires_at,
+            tags,
+            parts,
+        } = full;
+
+        let parts = parts
+            .into_iter()
+            .enumerate()
+            .map(|(i, part)| {
+                Ok(Part {
+                    mime_type: part.mime_type,
+                    content: content(part.content, Some(i))?,
+                })
+            })
+            .collect::<Result<_, E>>()?;
+
+        Ok(Self {
+            id: entry.id,
+            last_updated: entry.last_updated,
+            mime_type: entry.mime_type,
+            pinned,
+            expires_at,
+            tags,
+            content: content(entry.content, None)?,
+            parts,
+        })
+    }
+}
+
+impl Entry {
+    /// Convert the entry back, using the given function to get each piece of content.
+    pub fn into_full<E>(
+        self,
+        mut content: impl FnMut(Content) -> Result<Vec<u8>, E>,
+    ) -> Result<FullEntry, E> {
+        let parts = self
+            .parts
+            .into_iter()
+            .map(|part| {
+                Ok(EntryPart {
+                    mime_type: part.mime_type,
+                    content: content(part.content)?,
+                })
+            })
+            .collect::<Result<_, E>>()?;
+
+        Ok(FullEntry {
+            entry: ClipboardEntry {
+                id: self.id,
+                content: content(self.content)?,
+                last_updated: self.last_updated,
+                mime_type: self.mime_type,
+            },
+            pinned: self.pinned,
+            expires_at: self.expires_at,
+            tags: self.tags,
+            parts,
+        })
+    }
+}
+
+/// Serialize bytes as a base64 string.
+mod base64_bytes {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&BASE64.encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        BASE64.decode(s).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_entry_json() {
+        let full = FullEntry {
+            entry: ClipboardEntry {
+                id: 3,
+                content: b"hello".to_vec(),
+                last_updated: 1_700_000_000,
+                mime_type: Some("text/plain".into()),
+            },
+            pinned: true,
+            expires_at: None,
+            tags: vec!["work".into()],
+            parts: vec![EntryPart {
+                mime_type: "text/html".into(),
+                content: b"<b>hello</b>".to_vec(),
+            }],
+        };
+
+        let inline = Entry::new(full.clone(), |c, _| Ok::<_, ()>(Content::Inline(c))).unwrap();
+        let json = serde_json::to_string(&inline).unwrap();
+        assert_eq!(
+            json,
+            r#"{"id":3,"last_updated":1700000000,"mime_type":"text/plain","pinned":true,"tags":["work"],"content":"aGVsbG8=","parts":[{"mime_type":"text/html","content":"PGI+aGVsbG88L2I+"}]}"#
+        );
+        assert_eq!(serde_json::from_str::<Entry>(&json).unwrap(), inline);
+        assert_eq!(
+            inline
+                .into_full(|c| match c {
+                    Content::Inline(c) => Ok(c),
+                    Content::File(_) => Err(()),
+                })
+                .unwrap(),
+            full
+        );
+
+        let files = Entry::new(full, |_, i| {
+            Ok::<_, ()>(Content::File(match i {
+                None => "3".into(),
+                Some(i) => format!("3.{i}").into(),
+            }))
+        })
+        .unwrap();
+        let json = serde_json::to_string(&files).unwrap();
+        assert!(json.contains(r#""file":"3","parts":[{"mime_type":"text/html","file":"3.0"}]"#));
+        assert_eq!(serde_json::from_str::<Entry>(&json).unwrap(), files);
+
+        // The expiry is only included if set
+        let expiring = Entry {
+            expires_at: Some(1_700_000_300),
+            ..files
+        };
+        let json = serde_json::to_string(&expiring).unwrap();
+        assert!(json.contains(r#""pinned":true,"expires_at":1700000300,"tags""#));
+        assert_eq!(serde_json::from_str::<Entry>(&json).unwrap(), expiring);
+    }
+}
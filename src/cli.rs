@@ -1,9 +1,14 @@
-use std::{path::PathBuf, str::FromStr};
+use std::{ffi::OsString, fmt::Display, path::PathBuf, str::FromStr};
 
-use clap::{Parser, Subcommand, ValueHint, command};
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand, ValueHint, parser::ValueSource};
+use miette::Result;
 use regex::Regex;
+use serde::Deserialize;
 
-use crate::defaults;
+use crate::{
+    config::Config, database::data::EntryKind, defaults, rules::Rule, secrets::SecretRule,
+    template::Template,
+};
 
 #[derive(Parser, Debug)]
 #[command(version, about)]
@@ -23,6 +28,82 @@ pub struct Cli {
         global = true
     )]
     pub database: PathBuf,
+
+    /// Path to the TOML config file.
+    ///
+    /// Values from the config file are only used for arguments which were not given through the
+    /// CLI or an environment variable. Ignored if no file exists at the default path.
+    #[arg(
+        long,
+        default_value = defaults::CONFIG_PATH.to_str(),
+        value_hint = ValueHint::FilePath,
+        env = "CLIPVAULT_CONFIG",
+        global = true
+    )]
+    pub config: PathBuf,
+
+    /// How long to wait for other processes using the database to finish, before failing.
+    ///
+    /// e.g. a `store` from `wl-paste --watch` while a picker is running `list`.
+    #[arg(
+        long,
+        default_value = defaults::BUSY_TIMEOUT,
+        env = "CLIPVAULT_BUSY_TIMEOUT",
+        global = true
+    )]
+    pub busy_timeout: humantime::Duration,
+
+    #[command(flatten)]
+    pub key: KeyArgs,
+}
+
+/// Key used to encrypt and decrypt the content of entries.
+#[derive(Debug, Default, clap::Args)]
+pub struct KeyArgs {
+    /// Path to a file containing the key for an encrypted database.
+    ///
+    /// The whole content of the file is used as the key. If given for a new database, the content
+    /// of entries is encrypted with it.
+    #[arg(
+        long,
+        value_hint = ValueHint::FilePath,
+        env = "CLIPVAULT_KEY_FILE",
+        global = true
+    )]
+    pub key_file: Option<PathBuf>,
+
+    /// Passphrase for an encrypted database, used in place of a key file.
+    ///
+    /// Prefer setting the environment variable, to keep the passphrase out of the shell history.
+    #[arg(
+        long,
+        env = "CLIPVAULT_PASSPHRASE",
+        hide_env_values = true,
+        conflicts_with = "key_file",
+        global = true
+    )]
+    pub passphrase: Option<String>,
+}
+
+impl Cli {
+    /// Parse the given arguments, filling in any values which were not explicitly provided
+    /// from the config file.
+    ///
+    /// Precedence is: CLI > environment variables > config file > defaults.
+    pub fn parse_with_config<I, T>(args: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString> + Clone,
+    {
+        let matches = Self::command().get_matches_from(args);
+        let mut cli = Self::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+
+        // A missing config file is only an error if the path was explicitly given
+        let required = matches.value_source("config") != Some(ValueSource::DefaultValue);
+        Config::load(&cli.config, required)?.apply(&mut cli, &matches);
+
+        Ok(cli)
+    }
 }
 
 #[derive(Debug, Subcommand)]
@@ -33,15 +114,96 @@ pub enum Commands {
     /// Store an entry. Only accepts STDIN.
     #[command()]
     Store(StoreArgs),
+    /// Pause capturing entries, e.g. while screen sharing. `store` ignores its input until resumed.
+    #[command()]
+    Pause(PauseArgs),
+    /// Resume capturing entries after `pause`.
+    #[command()]
+    Resume,
+    /// Print whether capturing entries is paused, and until when.
+    ///
+    /// Prints `active`, `paused`, or `paused until` followed by the time in RFC 3339 format and the
+    /// time left.
+    #[command()]
+    Status,
     /// Get the content of an entry.
     #[command()]
-    Get(GetDelArgs),
+    Get(GetArgs),
     /// Delete an entry.
     #[command()]
     Delete(GetDelArgs),
-    /// Delete all entries.
+    /// Delete all entries, apart from pinned entries.
     #[command()]
-    Clear,
+    Clear(ClearArgs),
+    /// Pin an entry, so that it is not deleted when pruning or clearing the history.
+    #[command()]
+    Pin(GetDelArgs),
+    /// Unpin an entry.
+    #[command()]
+    Unpin(GetDelArgs),
+    /// Delete an entry once the given time has passed, e.g. for one-time codes.
+    ///
+    /// Expired entries are deleted by the next command which opens the database, even if they are
+    /// pinned or tagged.
+    #[command()]
+    Expire(ExpireArgs),
+    /// Check the rules for storing entries from the config file.
+    #[command(subcommand)]
+    Rules(RulesCommand),
+    /// Manage the tags of entries, used to group related entries.
+    #[command(subcommand)]
+    Tag(TagCommand),
+    /// Manage snippets - named entries which are kept separately from the history, until removed.
+    #[command(subcommand)]
+    Snippet(SnippetCommand),
+    /// Search the full text of text entries, with the same output as `list`.
+    #[command()]
+    Search(SearchArgs),
+    /// Export entries in the history with their content, e.g. to back them up or move them to
+    /// another machine.
+    ///
+    /// Entries are written oldest first as newline-delimited JSON, starting with a header line.
+    #[command()]
+    Export(ExportArgs),
+    /// Import entries from `clipvault export`, or from the database of cliphist.
+    ///
+    /// Entries keep their original timestamps, and are merged with existing entries with the same
+    /// content. The same size limits and ignore patterns as for `store` apply. Nothing is imported
+    /// if any entry can't be read.
+    #[command()]
+    Import(ImportArgs),
+    /// Merge the entries of another clipvault database into this one, e.g. from another machine.
+    ///
+    /// Entries with the same content are merged, keeping the newest timestamp, and pins, tags and
+    /// additional representations are carried over. The history is then pruned as for `store`.
+    /// Both databases must be unencrypted, or encrypted with the same key. Snippets are not
+    /// merged.
+    #[command()]
+    Merge(MergeArgs),
+    /// Rebuild the database to reduce its size as much as possible, and print the size before and
+    /// after.
+    ///
+    /// Space freed by deleting entries is reclaimed automatically, so this is only needed
+    /// occasionally to defragment the database.
+    #[command(visible_alias = "maintenance")]
+    Vacuum,
+    /// Encrypt the content of every entry, using the given key file or passphrase.
+    ///
+    /// Metadata such as the MIME type, size and time of each entry is not encrypted.
+    #[command()]
+    Encrypt,
+    /// Decrypt the content of every entry, disabling encryption.
+    #[command()]
+    Decrypt,
+    /// Re-encrypt the content of every entry with a new key.
+    #[command()]
+    Rekey(RekeyArgs),
+    /// Generate thumbnails for image entries, and print their paths.
+    ///
+    /// Thumbnails are PNG files named after the ID of the entry. Missing or outdated thumbnails
+    /// are generated, and thumbnails for entries which no longer exist are deleted.
+    #[command()]
+    Thumbnail(ThumbnailArgs),
 }
 
 #[derive(Debug, clap::Args)]
@@ -85,6 +247,221 @@ pub struct StoreArgs {
     /// of a regex engine used does not support those features.
     #[arg(long, action, env = "CLIPVAULT_IGNORE_PATTERN", num_args = 1)]
     pub ignore_pattern: Option<Vec<Regex>>,
+
+    #[command(flatten)]
+    pub ignore: IgnoreArgs,
+
+    /// MIME type of the entry, e.g. `text/html`. Detected from the content if not given.
+    ///
+    /// e.g. wl-paste --type text/html --watch clipvault store --mime-type text/html
+    #[arg(long, value_parser = parse_mime_type)]
+    pub mime_type: Option<String>,
+
+    /// Additional representation of the entry, given as `MIME=PATH`.
+    ///
+    /// The content read from STDIN remains the main representation, used for previews and by
+    /// default for `get`. Existing representations of the entry are only replaced if any are given.
+    /// To specify multiple representations, simply call the argument again.
+    ///
+    /// e.g. wl-paste --type text/plain | clipvault store --part text/html=<(wl-paste --type text/html)
+    #[arg(long, value_parser = parse_part, num_args = 1, value_name = "MIME=PATH")]
+    pub part: Vec<(String, PathBuf)>,
+
+    /// Also delete pinned entries when over the maximum number of entries or the maximum age.
+    ///
+    /// By default, pinned entries are kept and do not count towards the maximum number of entries.
+    #[arg(long, action, env = "CLIPVAULT_PRUNE_PINNED")]
+    pub prune_pinned: bool,
+
+    /// Tag to add to the entry. To specify multiple tags, simply call the argument again.
+    ///
+    /// e.g. wl-paste | clipvault store --tag screenshots
+    #[arg(long, value_parser = parse_tag, num_args = 1)]
+    pub tag: Vec<String>,
+
+    /// Don't delete entries with any tags when over the maximum number of entries or the maximum
+    /// age. Kept entries do not count towards the maximum number of entries.
+    #[arg(long, action, env = "CLIPVAULT_KEEP_TAGGED")]
+    pub keep_tagged: bool,
+
+    /// Delete the entry once this much time has passed, instead of keeping it for the maximum age.
+    /// Storing the same content again restarts it.
+    ///
+    /// e.g. wl-paste | clipvault store --ttl 30s
+    #[arg(long)]
+    pub ttl: Option<humantime::Duration>,
+
+    /// What to do with text which looks like a secret, e.g. an API key, private key or credit card
+    /// number.
+    #[arg(long, value_enum, default_value_t, env = "CLIPVAULT_ON_SECRET")]
+    pub on_secret: SecretAction,
+
+    /// How long to keep entries which look like secrets when using `--on-secret expire`, as for
+    /// `--ttl`. The shorter of the two is used if both apply.
+    #[arg(long, default_value = defaults::SECRET_TTL, env = "CLIPVAULT_SECRET_TTL")]
+    pub secret_ttl: humantime::Duration,
+
+    /// Additional rule for detecting secrets, given as `NAME=REGEX`. The name is included in the
+    /// debug logs when the rule matches. To specify multiple rules, simply call the argument again.
+    ///
+    /// e.g. clipvault store --secret-pattern 'vault=\bhvs\.[A-Za-z0-9]{24,}'
+    #[arg(
+        long,
+        num_args = 1,
+        value_name = "NAME=REGEX",
+        env = "CLIPVAULT_SECRET_PATTERN"
+    )]
+    pub secret_pattern: Vec<SecretRule>,
+
+    /// Also treat text which is a single random-looking token, e.g. a generated password, as a
+    /// secret. Off by default, as identifiers and file names can look just as random.
+    #[arg(long, action, env = "CLIPVAULT_SECRET_ENTROPY")]
+    pub secret_entropy: bool,
+
+    /// Name of the application the entry was copied from, checked by the `source` condition of
+    /// rules in the config file.
+    ///
+    /// e.g. clipvault store --source "$(hyprctl activewindow -j | jq -r .class)"
+    #[arg(long, env = "CLIPVAULT_SOURCE")]
+    pub source: Option<String>,
+
+    /// Rules from the config file, checked in order.
+    #[arg(skip)]
+    pub rules: Vec<Rule>,
+}
+
+/// Ways to ignore entries which also apply to binary content, unlike `--ignore-pattern`. Shared by
+/// `store` and `import`.
+#[derive(Debug, Default, Clone, clap::Args)]
+pub struct IgnoreArgs {
+    /// Entries with a MIME type matching the given glob pattern are ignored, e.g. `image/gif` or
+    /// `application/*`. The MIME type is detected from the content if not given.
+    ///
+    /// Patterns are case-sensitive and also support sets like `image/[pj]*`, as for `list --mime`.
+    ///
+    /// To specify multiple patterns, simply call the argument again with a new pattern.
+    #[arg(long, num_args = 1, value_name = "GLOB", env = "CLIPVAULT_IGNORE_MIME")]
+    pub ignore_mime: Vec<String>,
+
+    /// Entries which include any match for the given regex pattern in their raw bytes are ignored.
+    /// Unlike `--ignore-pattern`, this applies to binary content, and `\xNN` matches the
+    /// byte NN, e.g. to match a file signature.
+    ///
+    /// To specify multiple patterns, simply call the argument again with a new pattern.
+    ///
+    /// e.g. clipvault store --ignore-bytes '^\x89PNG' --ignore-bytes '^GIF8[79]a'
+    #[arg(
+        long,
+        num_args = 1,
+        value_name = "REGEX",
+        env = "CLIPVAULT_IGNORE_BYTES"
+    )]
+    pub ignore_bytes: Vec<BytePattern>,
+
+    /// Images narrower or shorter than this are ignored, e.g. icons.
+    #[arg(long, value_name = "WIDTHxHEIGHT", env = "CLIPVAULT_MIN_IMAGE_SIZE")]
+    pub min_image_size: Option<Dimensions>,
+
+    /// Images wider or taller than this are ignored, e.g. full-resolution screenshots.
+    #[arg(long, value_name = "WIDTHxHEIGHT", env = "CLIPVAULT_MAX_IMAGE_SIZE")]
+    pub max_image_size: Option<Dimensions>,
+}
+
+/// A regex which is matched against raw bytes rather than text, so that `\xNN` matches the byte
+/// NN and `.` matches any byte.
+#[derive(Debug, Clone)]
+pub struct BytePattern(pub regex::bytes::Regex);
+
+impl FromStr for BytePattern {
+    type Err = regex::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        regex::bytes::RegexBuilder::new(s)
+            .unicode(false)
+            .build()
+            .map(Self)
+    }
+}
+
+/// Pixel dimensions of an image, given as `WIDTHxHEIGHT`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Dimensions {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl FromStr for Dimensions {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split_once('x')
+            .and_then(|(width, height)| {
+                Some(Self {
+                    width: width.parse().ok()?,
+                    height: height.parse().ok()?,
+                })
+            })
+            .ok_or_else(|| format!("expected WIDTHxHEIGHT, got: {s}"))
+    }
+}
+
+impl Display for Dimensions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}x{}", self.width, self.height)
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SecretAction {
+    /// Store it like any other entry.
+    Keep,
+    /// Store it, but delete it after `--secret-ttl`.
+    #[default]
+    Expire,
+    /// Don't store it.
+    Reject,
+}
+
+/// Basic validation for a MIME type given by the user, i.e. `type/subtype` without whitespace.
+fn parse_mime_type(s: &str) -> Result<String, String> {
+    match s.split_once('/') {
+        Some((t, sub)) if !t.is_empty() && !sub.is_empty() && !s.contains(char::is_whitespace) => {
+            Ok(s.to_owned())
+        }
+        _ => Err(format!("invalid MIME type: {s}")),
+    }
+}
+
+/// Tags are used in comma-separated output, so can't contain commas or whitespace.
+pub(crate) fn parse_tag(s: &str) -> Result<String, String> {
+    if s.is_empty() || s.contains(|c: char| c == ',' || c.is_whitespace()) {
+        Err(format!(
+            "invalid tag: '{s}' - tags must not be empty or contain commas or whitespace"
+        ))
+    } else {
+        Ok(s.to_owned())
+    }
+}
+
+/// Snippet names are used in place of IDs in `list` output, so can't contain whitespace.
+fn parse_snippet_name(s: &str) -> Result<String, String> {
+    if s.is_empty() || s.contains(char::is_whitespace) {
+        Err(format!(
+            "invalid snippet name: '{s}' - names must not be empty or contain whitespace"
+        ))
+    } else {
+        Ok(s.to_owned())
+    }
+}
+
+/// Parse an additional entry representation in the form `MIME=PATH`.
+fn parse_part(s: &str) -> Result<(String, PathBuf), String> {
+    let (mime_type, path) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected MIME=PATH, got: {s}"))?;
+
+    Ok((parse_mime_type(mime_type)?, PathBuf::from(path)))
 }
 
 impl Default for StoreArgs {
@@ -97,6 +474,20 @@ impl Default for StoreArgs {
             min_entry_length: defaults::MIN_ENTRY_LEN,
             store_sensitive: false,
             ignore_pattern: None,
+            ignore: IgnoreArgs::default(),
+            mime_type: None,
+            part: Vec::new(),
+            prune_pinned: false,
+            tag: Vec::new(),
+            keep_tagged: false,
+            ttl: None,
+            on_secret: SecretAction::default(),
+            secret_ttl: humantime::Duration::from_str(defaults::SECRET_TTL)
+                .expect("default secret TTL should be valid"),
+            secret_pattern: Vec::new(),
+            secret_entropy: false,
+            source: None,
+            rules: Vec::new(),
         }
     }
 }
@@ -105,7 +496,8 @@ impl Default for StoreArgs {
 pub struct ListArgs {
     /// Maximum width in characters for the previews.
     ///
-    /// Setting this value to 0 disables the limit.
+    /// Setting this value to 0 disables the limit, though previews of text are always limited to
+    /// the first 1000 characters.
     #[arg(
         short,
         long,
@@ -117,6 +509,47 @@ pub struct ListArgs {
     /// Reverse the order of the list of returned entries (oldest first).
     #[arg(short, long, action, env = "CLIPVAULT_REVERSE_LIST")]
     pub reverse: bool,
+
+    /// List pinned entries before all other entries.
+    #[arg(long, action, env = "CLIPVAULT_PINNED_FIRST")]
+    pub pinned_first: bool,
+
+    /// Include snippets, listed before all other entries and not affected by filters. Ignored by
+    /// `clipvault search`.
+    ///
+    /// Snippets use their name prefixed with `:` in place of an ID, e.g. `:ssh-prod`, which is
+    /// also accepted by `clipvault get`.
+    #[arg(long, action, env = "CLIPVAULT_LIST_SNIPPETS")]
+    pub snippets: bool,
+
+    /// Output format.
+    #[arg(long, value_enum, default_value_t, env = "CLIPVAULT_LIST_FORMAT")]
+    pub format: ListFormat,
+
+    /// Template for each line of output, replacing the default `{id}\t{preview}`.
+    ///
+    /// Placeholders: {id}, {preview}, {mime}, {kind}, {size}, {width}, {height}, {age},
+    /// {last_updated}, {pinned}, {tags}, {thumbnail}. Values which don't apply to an entry, e.g.
+    /// {width} for text, are empty. {pinned} is `*` for pinned entries, and {tags} is a
+    /// comma-separated list.
+    ///
+    /// {thumbnail} is the path to a thumbnail of image entries, which are generated as needed (see
+    /// `clipvault thumbnail`).
+    ///
+    /// Escape sequences: \n, \t, \0, \\ and \xHH (e.g. \x1f). Use {{ and }} for literal braces.
+    ///
+    /// Only supported for the plain output format. Note that the output must still start with the
+    /// ID for it to be passed to `clipvault get`.
+    ///
+    /// e.g. clipvault list --template '{id}\t{preview}\0info\x1f{id}'
+    #[arg(long, env = "CLIPVAULT_LIST_TEMPLATE")]
+    pub template: Option<Template>,
+
+    #[command(flatten)]
+    pub filter: FilterArgs,
+
+    #[command(flatten)]
+    pub thumbnails: ThumbnailArgs,
 }
 
 impl Default for ListArgs {
@@ -124,10 +557,317 @@ impl Default for ListArgs {
         Self {
             max_preview_width: defaults::MAX_PREVIEW_WIDTH,
             reverse: false,
+            pinned_first: false,
+            snippets: false,
+            format: ListFormat::default(),
+            template: None,
+            filter: FilterArgs::default(),
+            thumbnails: ThumbnailArgs::default(),
         }
     }
 }
 
+/// Conditions for which entries are listed.
+#[derive(Debug, Default, clap::Args)]
+pub struct FilterArgs {
+    /// Only list pinned entries.
+    #[arg(long, action)]
+    pub pinned: bool,
+
+    /// Only list entries with the given tag.
+    #[arg(long, value_parser = parse_tag)]
+    pub tag: Option<String>,
+
+    /// Only list entries of the given kind.
+    #[arg(long = "type", value_enum)]
+    pub kind: Option<EntryKind>,
+
+    /// Only list entries with a MIME type matching the given glob pattern, using SQLite's GLOB
+    /// syntax: `*`, `?` and sets like `[a-z]`, matched case-sensitively.
+    ///
+    /// e.g. clipvault list --mime 'image/*'
+    #[arg(long, value_name = "PATTERN")]
+    pub mime: Option<String>,
+
+    /// Only list entries which are at least this many bytes in size.
+    #[arg(long)]
+    pub min_size: Option<usize>,
+
+    /// Only list entries which are at most this many bytes in size.
+    #[arg(long)]
+    pub max_size: Option<usize>,
+
+    /// Only list entries copied within the given time, e.g. `1h`.
+    ///
+    /// See the `humantime` crate for the supported format.
+    #[arg(long)]
+    pub since: Option<humantime::Duration>,
+
+    /// Only list entries copied at least the given time ago, e.g. `30m`.
+    ///
+    /// See the `humantime` crate for the supported format.
+    #[arg(long)]
+    pub until: Option<humantime::Duration>,
+
+    /// Maximum number of entries to list.
+    #[arg(long)]
+    pub limit: Option<usize>,
+
+    /// Number of entries to skip, e.g. for pagination with `--limit`.
+    ///
+    /// Entries are skipped from the most recent, before `--reverse` is applied.
+    #[arg(long, default_value_t = 0)]
+    pub offset: usize,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ListFormat {
+    /// One entry per line: the ID and a preview, separated by a tab.
+    #[default]
+    Plain,
+    /// A JSON array of objects, including metadata such as the size and MIME type.
+    Json,
+    /// Newline-delimited JSON - one object per line, with the same fields as `json`.
+    Ndjson,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct ExportArgs {
+    /// Layout of the export.
+    #[arg(long, value_enum, default_value_t)]
+    pub format: ExportFormat,
+
+    /// File to write to, or the directory for the `dir` format. Defaults to STDOUT for `ndjson`.
+    #[arg(
+        short,
+        long,
+        value_hint = ValueHint::AnyPath,
+        required_if_eq("format", "dir")
+    )]
+    pub output: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub filter: FilterArgs,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ExportFormat {
+    /// A single file, with the content of each entry embedded as base64.
+    #[default]
+    Ndjson,
+    /// A directory containing a file for each piece of content, and an `index.ndjson` file which
+    /// refers to them.
+    Dir,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct ImportArgs {
+    /// Path to an export file or directory, or to a cliphist database. The format is detected
+    /// automatically.
+    ///
+    /// An export file is read from STDIN if not given.
+    ///
+    /// e.g. clipvault import ~/.cache/cliphist/db
+    #[arg(value_hint = ValueHint::AnyPath)]
+    pub path: Option<PathBuf>,
+
+    /// Entries that are larger than this value in bytes will not be imported.
+    ///
+    /// Setting this value to 0 disables the limit.
+    #[arg(long, default_value_t = defaults::MAX_ENTRY_LEN, env = "CLIPVAULT_MAX_LENGTH")]
+    pub max_entry_length: usize,
+
+    /// Entries that are smaller than this value in bytes will not be imported.
+    #[arg(long, default_value_t = defaults::MIN_ENTRY_LEN, env = "CLIPVAULT_MIN_LENGTH")]
+    pub min_entry_length: usize,
+
+    /// Entries which include any match for the given regex pattern will not be imported.
+    ///
+    /// To specify multiple patterns, simply call the argument again with a new pattern.
+    #[arg(long, action, env = "CLIPVAULT_IGNORE_PATTERN", num_args = 1)]
+    pub ignore_pattern: Option<Vec<Regex>>,
+
+    #[command(flatten)]
+    pub ignore: IgnoreArgs,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct MergeArgs {
+    /// Path to the database to merge entries from. It is upgraded to the current schema if needed,
+    /// but its entries are left untouched.
+    ///
+    /// e.g. clipvault merge --from ~/backup/clipvault.db
+    #[arg(long, value_hint = ValueHint::FilePath)]
+    pub from: PathBuf,
+
+    /// Maximum number of entries to keep after merging.
+    ///
+    /// Setting this value to 0 disables the limit.
+    #[arg(long, default_value_t = defaults::MAX_ENTRIES, env = "CLIPVAULT_MAX_ENTRIES")]
+    pub max_entries: usize,
+
+    /// Entries older than this value will be deleted after merging. Only accurate to the second.
+    ///
+    /// Setting this value to 0s or less disables the limit.
+    #[arg(long, default_value = defaults::MAX_ENTRY_AGE, env = "CLIPVAULT_MAX_AGE")]
+    pub max_entry_age: humantime::Duration,
+
+    /// Also delete pinned entries when over the maximum number of entries or the maximum age.
+    #[arg(long, action, env = "CLIPVAULT_PRUNE_PINNED")]
+    pub prune_pinned: bool,
+
+    /// Don't delete entries with any tags when over the maximum number of entries or the maximum
+    /// age.
+    #[arg(long, action, env = "CLIPVAULT_KEEP_TAGGED")]
+    pub keep_tagged: bool,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum RulesCommand {
+    /// Check which rule would apply when storing the content read from STDIN, without storing it,
+    /// and print its name and actions.
+    #[command()]
+    Test(RulesTestArgs),
+}
+
+#[derive(Debug, clap::Args)]
+pub struct RulesTestArgs {
+    /// MIME type of the content, as for `store`. Detected from the content if not given.
+    #[arg(long, value_parser = parse_mime_type)]
+    pub mime_type: Option<String>,
+
+    /// Value of `CLIPBOARD_STATE` to check the rules with.
+    #[arg(long)]
+    pub clipboard_state: Option<String>,
+
+    /// Application the content was copied from, as for `store`.
+    #[arg(long)]
+    pub source: Option<String>,
+
+    /// Local time of day to check the rules at, e.g. `09:30`. Defaults to now.
+    #[arg(long, value_name = "HH:MM")]
+    pub at: Option<jiff::civil::Time>,
+
+    /// Rules from the config file, checked in order.
+    #[arg(skip)]
+    pub rules: Vec<Rule>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum TagCommand {
+    /// Add a tag to an entry.
+    #[command()]
+    Add(TagEntryArgs),
+    /// Remove a tag from an entry.
+    #[command()]
+    Remove(TagEntryArgs),
+    /// List every tag and its number of entries, or the tags of a single entry.
+    #[command()]
+    List(TagListArgs),
+}
+
+#[derive(Debug, clap::Args)]
+pub struct TagEntryArgs {
+    /// Name of the tag, which can't contain commas or whitespace.
+    #[arg(value_parser = parse_tag)]
+    pub tag: String,
+
+    #[command(flatten)]
+    pub entry: GetDelArgs,
+}
+
+#[derive(Debug, Default, clap::Args)]
+pub struct TagListArgs {
+    /// The selected row from `clipvault list`, or just the ID of the entry.
+    ///
+    /// If neither this nor `--index` is given, every tag is listed instead. STDIN is not read.
+    pub input: Option<String>,
+
+    /// The relative index of the entry (starting at 0), as for `clipvault get`.
+    #[arg(long, conflicts_with("input"), allow_hyphen_values(true))]
+    pub index: Option<isize>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum SnippetCommand {
+    /// Save a snippet, read from STDIN or copied from an entry in the history.
+    #[command()]
+    Add(SnippetAddArgs),
+    /// Get the content of a snippet.
+    #[command()]
+    Get(SnippetArgs),
+    /// List the name and a preview of every snippet.
+    #[command()]
+    List,
+    /// Remove a snippet.
+    #[command()]
+    Rm(SnippetArgs),
+    /// Edit a text snippet using $VISUAL or $EDITOR.
+    #[command()]
+    Edit(SnippetArgs),
+}
+
+#[derive(Debug, clap::Args)]
+pub struct SnippetArgs {
+    /// Name of the snippet, which can't contain whitespace.
+    #[arg(value_parser = parse_snippet_name)]
+    pub name: String,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct SnippetAddArgs {
+    /// Name of the snippet, which can't contain whitespace.
+    #[arg(value_parser = parse_snippet_name)]
+    pub name: String,
+
+    /// Copy an entry from the history - the selected row from `clipvault list`, or just the ID of
+    /// the entry. STDIN is not read.
+    #[arg(long)]
+    pub entry: Option<String>,
+
+    /// Copy an entry from the history by its relative index (starting at 0), as for
+    /// `clipvault get`. STDIN is not read.
+    #[arg(long, conflicts_with("entry"), allow_hyphen_values(true))]
+    pub index: Option<isize>,
+
+    /// MIME type of the content read from STDIN. Detected from the content if not given.
+    #[arg(long, value_parser = parse_mime_type, conflicts_with_all(["entry", "index"]))]
+    pub mime_type: Option<String>,
+
+    /// Replace an existing snippet with the same name.
+    #[arg(short, long, action)]
+    pub force: bool,
+}
+
+#[derive(Debug, Default, clap::Args)]
+pub struct ClearArgs {
+    /// Also delete pinned entries.
+    #[arg(long, action)]
+    pub include_pinned: bool,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct RekeyArgs {
+    /// Path to a file containing the new key.
+    #[arg(
+        long,
+        value_hint = ValueHint::FilePath,
+        env = "CLIPVAULT_NEW_KEY_FILE",
+        required_unless_present = "new_passphrase"
+    )]
+    pub new_key_file: Option<PathBuf>,
+
+    /// New passphrase, used in place of a key file.
+    #[arg(
+        long,
+        env = "CLIPVAULT_NEW_PASSPHRASE",
+        hide_env_values = true,
+        conflicts_with = "new_key_file"
+    )]
+    pub new_passphrase: Option<String>,
+}
+
 #[derive(Debug, Default, clap::Args)]
 pub struct GetDelArgs {
     /// The selected row from `clipvault list`, or just the ID of the entry.
@@ -146,3 +886,104 @@ pub struct GetDelArgs {
     #[arg(long, conflicts_with("input"), allow_hyphen_values(true))]
     pub index: Option<isize>,
 }
+
+#[derive(Debug, clap::Args)]
+pub struct PauseArgs {
+    /// Resume automatically after this long, e.g. `10m`. Paused until `resume` if not given.
+    #[arg(long = "for", value_name = "DURATION")]
+    pub duration: Option<humantime::Duration>,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct ExpireArgs {
+    /// The selected row from `clipvault list`, or just the ID of the entry.
+    pub input: String,
+
+    /// How long to keep the entry from now, e.g. `30s` or `1h`.
+    pub duration: humantime::Duration,
+}
+
+#[derive(Debug, Default, clap::Args)]
+pub struct GetArgs {
+    #[command(flatten)]
+    pub entry: GetDelArgs,
+
+    /// Print the MIME type of the entry instead of its content.
+    ///
+    /// e.g. clipvault get 5 | wl-copy --type "$(clipvault get --print-mime-type 5)"
+    #[arg(long, action, conflicts_with_all(["mime", "list_mime_types"]))]
+    pub print_mime_type: bool,
+
+    /// Get the representation of the entry with the given MIME type, e.g. `text/html`.
+    ///
+    /// Fails if the entry has no representation with that MIME type.
+    #[arg(long, value_parser = parse_mime_type)]
+    pub mime: Option<String>,
+
+    /// Print the MIME types of all representations of the entry, one per line, starting with the
+    /// main representation.
+    #[arg(long, action, conflicts_with("mime"))]
+    pub list_mime_types: bool,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct ThumbnailArgs {
+    /// Directory in which thumbnails are stored.
+    #[arg(
+        long,
+        default_value = defaults::THUMBNAILS_DIR.to_str(),
+        value_hint = ValueHint::DirPath,
+        env = "CLIPVAULT_THUMBNAIL_DIR"
+    )]
+    pub thumbnail_dir: PathBuf,
+
+    /// Maximum width and height of thumbnails in pixels. The aspect ratio is preserved.
+    ///
+    /// Existing thumbnails are not regenerated if this value changes.
+    #[arg(
+        long,
+        default_value_t = defaults::THUMBNAIL_SIZE,
+        value_parser = clap::value_parser!(u32).range(1..),
+        env = "CLIPVAULT_THUMBNAIL_SIZE"
+    )]
+    pub thumbnail_size: u32,
+}
+
+impl Default for ThumbnailArgs {
+    fn default() -> Self {
+        Self {
+            thumbnail_dir: defaults::THUMBNAILS_DIR.clone(),
+            thumbnail_size: defaults::THUMBNAIL_SIZE,
+        }
+    }
+}
+
+#[derive(Debug, clap::Args)]
+pub struct SearchArgs {
+    /// The search query, using SQLite FTS5 syntax.
+    ///
+    /// Words must all be present in an entry for it to match, in any order. Supports phrases
+    /// ("hello world"), prefixes (hel*), and the operators AND, OR and NOT. Matching is
+    /// case-insensitive.
+    pub query: String,
+
+    /// Match the query as a single phrase, ignoring any special syntax.
+    #[arg(long, action)]
+    pub literal: bool,
+
+    /// Order of the results.
+    #[arg(long, value_enum, default_value_t)]
+    pub order: SearchOrder,
+
+    #[command(flatten)]
+    pub list: ListArgs,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum SearchOrder {
+    /// Most relevant entries first.
+    #[default]
+    Rank,
+    /// Most recently updated entries first, like `list`.
+    Recent,
+}
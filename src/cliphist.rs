@@ -0,0 +1,417 @@
+//! Read-only support for the database of [cliphist](https://github.com/sentriz/cliphist), so that
+//! its history can be imported.
+//!
+//! cliphist uses [bbolt](https://github.com/etcd-io/bbolt), which stores a B+ tree of pages in a
+//! single file. The history is kept in a bucket named `b`, keyed by the big-endian ID of each
+//! entry, with the raw content as the value. Only as much of the format as is needed to read that
+//! bucket is implemented.
+
+use miette::{Result, miette};
+
+/// Magic number at the start of both meta pages.
+const MAGIC: u32 = 0xED0C_DAED;
+/// Version of the file format.
+const VERSION: u32 = 2;
+
+const PAGE_HEADER_SIZE: usize = 16;
+/// Size of the meta fields which are covered by the checksum.
+const META_CHECKSUMMED_SIZE: usize = 56;
+const ELEMENT_SIZE: usize = 16;
+const BUCKET_HEADER_SIZE: usize = 16;
+
+const BRANCH_PAGE: u16 = 0x01;
+const LEAF_PAGE: u16 = 0x02;
+/// Flag of leaf elements whose value is a nested bucket.
+const BUCKET_LEAF: u32 = 0x01;
+
+/// Name of the bucket containing the history.
+const HISTORY_BUCKET: &[u8] = b"b";
+
+/// Used when the first meta page is invalid, as its page size can't be trusted.
+const DEFAULT_PAGE_SIZE: usize = 4096;
+/// Guards against cycles in corrupted files. Real trees are only a few levels deep.
+const MAX_DEPTH: usize = 64;
+
+fn corrupt() -> miette::Report {
+    miette!("the cliphist database is corrupted")
+}
+
+/// Add offsets or sizes read from the file, which could overflow if it is corrupted.
+fn add(a: usize, b: usize) -> Result<usize> {
+    a.checked_add(b).ok_or_else(corrupt)
+}
+
+fn read<const N: usize>(data: &[u8], offset: usize) -> Result<[u8; N]> {
+    offset
+        .checked_add(N)
+        .and_then(|end| data.get(offset..end))
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(corrupt)
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Result<u16> {
+    read(data, offset).map(u16::from_le_bytes)
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32> {
+    read(data, offset).map(u32::from_le_bytes)
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Result<u64> {
+    read(data, offset).map(u64::from_le_bytes)
+}
+
+fn slice(data: &[u8], offset: usize, len: usize) -> Result<&[u8]> {
+    offset
+        .checked_add(len)
+        .and_then(|end| data.get(offset..end))
+        .ok_or_else(corrupt)
+}
+
+/// 64-bit FNV-1a hash, used for the checksum of meta pages.
+fn fnv1a(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &b| {
+        (hash ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Whether the data looks like the start of a bbolt database.
+pub fn is_cliphist_db(data: &[u8]) -> bool {
+    read_u32(data, PAGE_HEADER_SIZE).is_ok_and(|magic| magic == MAGIC)
+}
+
+/// Fields of a valid meta page.
+struct Meta {
+    page_size: usize,
+    root: u64,
+    txid: u64,
+}
+
+impl Meta {
+    /// Parse the meta page starting at the given offset, if it is valid.
+    fn parse(data: &[u8], offset: usize) -> Option<Self> {
+        let meta = slice(
+            data,
+            add(offset, PAGE_HEADER_SIZE).ok()?,
+            META_CHECKSUMMED_SIZE + 8,
+        )
+        .ok()?;
+        let checksum = read_u64(meta, META_CHECKSUMMED_SIZE).ok()?;
+
+        let valid = read_u32(meta, 0).ok()? == MAGIC
+            && read_u32(meta, 4).ok()? == VERSION
+            && fnv1a(meta.get(..META_CHECKSUMMED_SIZE)?) == checksum;
+        if !valid {
+            return None;
+        }
+
+        Some(Self {
+            page_size: read_u32(meta, 8).ok()? as usize,
+            root: read_u64(meta, 16).ok()?,
+            txid: read_u64(meta, 48).ok()?,
+        })
+    }
+}
+
+struct Db<'a> {
+    data: &'a [u8],
+    page_size: usize,
+}
+
+impl<'a> Db<'a> {
+    fn page(&self, id: u64) -> Result<&'a [u8]> {
+        let offset = usize::try_from(id)
+            .ok()
+            .and_then(|id| id.checked_mul(self.page_size))
+            .ok_or_else(corrupt)?;
+        let overflow = read_u32(self.data, add(offset, 12)?)? as usize;
+        let len = add(overflow, 1)?
+            .checked_mul(self.page_size)
+            .ok_or_else(corrupt)?;
+
+        slice(self.data, offset, len)
+    }
+
+    /// Call the function with the flags, key and value of every element in the tree starting at
+    /// the given page, in order of their keys.
+    fn walk(
+        &self,
+        page: &'a [u8],
+        depth: usize,
+        f: &mut impl FnMut(u32, &'a [u8], &'a [u8]) -> Result<()>,
+    ) -> Result<()> {
+        if depth > MAX_DEPTH {
+            return Err(corrupt());
+        }
+
+        let flags = read_u16(page, 8)?;
+        let count = read_u16(page, 10)? as usize;
+
+        for i in 0..count {
+            // Positions are relative to the start of the element
+            let element = PAGE_HEADER_SIZE + i * ELEMENT_SIZE;
+
+            match flags {
+                BRANCH_PAGE => {
+                    let child = read_u64(page, element + 8)?;
+                    self.walk(self.page(child)?, depth + 1, f)?;
+                }
+                LEAF_PAGE => {
+                    let element_flags = read_u32(page, element)?;
+                    let pos = read_u32(page, element + 4)? as usize;
+                    let key_size = read_u32(page, element + 8)? as usize;
+                    let value_size = read_u32(page, element + 12)? as usize;
+
+                    let key_offset = add(element, pos)?;
+                    let key = slice(page, key_offset, key_size)?;
+                    let value = slice(page, add(key_offset, key_size)?, value_size)?;
+                    f(element_flags, key, value)?;
+                }
+                _ => return Err(corrupt()),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Get the root page of the given bucket, which is stored inline for small buckets.
+    fn bucket(&self, root: &'a [u8], name: &[u8]) -> Result<Option<&'a [u8]>> {
+        let mut bucket = None;
+        self.walk(root, 0, &mut |flags, key, value| {
+            if flags & BUCKET_LEAF != 0 && key == name {
+                bucket = Some(value);
+            }
+            Ok(())
+        })?;
+
+        bucket
+            .map(|value| match read_u64(value, 0)? {
+                0 => value.get(BUCKET_HEADER_SIZE..).ok_or_else(corrupt),
+                id => self.page(id),
+            })
+            .transpose()
+    }
+}
+
+/// Read the content of every entry in the cliphist database, oldest first.
+pub fn read_entries(data: &[u8]) -> Result<Vec<&[u8]>> {
+    if !is_cliphist_db(data) {
+        return Err(miette!("not a cliphist database"));
+    }
+
+    // Two meta pages are written alternately, so use the latest valid one
+    let first = Meta::parse(data, 0);
+    let second_offset = first.as_ref().map_or(DEFAULT_PAGE_SIZE, |m| m.page_size);
+    let meta = [first, Meta::parse(data, second_offset)]
+        .into_iter()
+        .flatten()
+        .max_by_key(|m| m.txid)
+        .ok_or_else(corrupt)?;
+    if meta.page_size < PAGE_HEADER_SIZE {
+        return Err(corrupt());
+    }
+
+    let db = Db {
+        data,
+        page_size: meta.page_size,
+    };
+    let Some(bucket) = db.bucket(db.page(meta.root)?, HISTORY_BUCKET)? else {
+        tracing::debug!("no history bucket");
+        return Ok(Vec::new());
+    };
+
+    let mut entries = Vec::new();
+    db.walk(bucket, 0, &mut |flags, _, value| {
+        if flags & BUCKET_LEAF == 0 {
+            entries.push(value);
+        }
+        Ok(())
+    })?;
+
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    const PAGE_SIZE: usize = 512;
+
+    fn header(id: u64, flags: u16, count: usize) -> Vec<u8> {
+        let mut page = id.to_le_bytes().to_vec();
+        page.extend(flags.to_le_bytes());
+        page.extend((count as u16).to_le_bytes());
+        page.extend(0u32.to_le_bytes());
+        page
+    }
+
+    fn meta(id: u64, root: u64, txid: u64) -> Vec<u8> {
+        let mut meta = MAGIC.to_le_bytes().to_vec();
+        meta.extend(VERSION.to_le_bytes());
+        meta.extend((PAGE_SIZE as u32).to_le_bytes());
+        meta.extend(0u32.to_le_bytes());
+        meta.extend(root.to_le_bytes());
+        meta.extend(0u64.to_le_bytes());
+        // Freelist and high water mark
+        meta.extend(2u64.to_le_bytes());
+        meta.extend(8u64.to_le_bytes());
+        meta.extend(txid.to_le_bytes());
+        meta.extend(fnv1a(&meta).to_le_bytes());
+
+        let mut page = header(id, 0x04, 0);
+        page.extend(meta);
+        page
+    }
+
+    fn leaf(id: u64, elements: &[(u32, &[u8], &[u8])]) -> Vec<u8> {
+        let mut page = header(id, LEAF_PAGE, elements.len());
+        let mut data = Vec::<u8>::new();
+        for (i, (flags, key, value)) in elements.iter().enumerate() {
+            let pos = (elements.len() - i) * ELEMENT_SIZE + data.len();
+            page.extend(flags.to_le_bytes());
+            page.extend((pos as u32).to_le_bytes());
+            page.extend((key.len() as u32).to_le_bytes());
+            page.extend((value.len() as u32).to_le_bytes());
+            data.extend(*key);
+            data.extend(*value);
+        }
+        page.extend(data);
+        page
+    }
+
+    fn branch(id: u64, children: &[(&[u8], u64)]) -> Vec<u8> {
+        let mut page = header(id, BRANCH_PAGE, children.len());
+        let mut data = Vec::<u8>::new();
+        for (i, (key, child)) in children.iter().enumerate() {
+            let pos = (children.len() - i) * ELEMENT_SIZE + data.len();
+            page.extend((pos as u32).to_le_bytes());
+            page.extend((key.len() as u32).to_le_bytes());
+            page.extend(child.to_le_bytes());
+            data.extend(*key);
+        }
+        page.extend(data);
+        page
+    }
+
+    fn bucket_header(root: u64) -> Vec<u8> {
+        let mut header = root.to_le_bytes().to_vec();
+        header.extend(0u64.to_le_bytes());
+        header
+    }
+
+    fn db(pages: Vec<Vec<u8>>) -> Vec<u8> {
+        pages
+            .into_iter()
+            .flat_map(|mut page| {
+                assert!(page.len() <= PAGE_SIZE);
+                page.resize(PAGE_SIZE, 0);
+                page
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_read_inline_bucket() {
+        let bucket = [
+            bucket_header(0),
+            leaf(
+                0,
+                &[
+                    (0, &1u64.to_be_bytes(), b"first"),
+                    (0, &2u64.to_be_bytes(), b"second"),
+                ],
+            ),
+        ]
+        .concat();
+        let data = db(vec![
+            meta(0, 3, 0),
+            meta(1, 3, 1),
+            header(2, 0x10, 0),
+            leaf(3, &[(BUCKET_LEAF, HISTORY_BUCKET, &bucket)]),
+        ]);
+
+        assert!(is_cliphist_db(&data));
+        assert_eq!(
+            read_entries(&data).unwrap(),
+            vec![b"first".as_slice(), b"second"]
+        );
+    }
+
+    #[test]
+    fn test_read_branches() {
+        let data = db(vec![
+            meta(0, 3, 2),
+            // Outdated meta page pointing at an old root
+            meta(1, 7, 1),
+            header(2, 0x10, 0),
+            leaf(
+                3,
+                &[
+                    (BUCKET_LEAF, b"a", &bucket_header(7)),
+                    (BUCKET_LEAF, HISTORY_BUCKET, &bucket_header(4)),
+                ],
+            ),
+            branch(4, &[(&1u64.to_be_bytes(), 5), (&3u64.to_be_bytes(), 6)]),
+            leaf(
+                5,
+                &[
+                    (0, &1u64.to_be_bytes(), b"one"),
+                    (0, &2u64.to_be_bytes(), b"two"),
+                ],
+            ),
+            leaf(6, &[(0, &3u64.to_be_bytes(), b"three")]),
+            leaf(7, &[]),
+        ]);
+
+        assert_eq!(
+            read_entries(&data).unwrap(),
+            vec![b"one".as_slice(), b"two", b"three"]
+        );
+    }
+
+    #[test]
+    fn test_read_invalid() {
+        assert!(!is_cliphist_db(b"{\"format\":\"clipvault\"}"));
+        assert!(read_entries(b"").is_err());
+
+        // Both meta pages invalid
+        let mut data = db(vec![meta(0, 2, 0), meta(1, 2, 1), leaf(2, &[])]);
+        data[PAGE_HEADER_SIZE + 20] ^= 1;
+        data[PAGE_SIZE + PAGE_HEADER_SIZE + 20] ^= 1;
+        assert!(read_entries(&data).is_err());
+
+        // Missing history bucket
+        let data = db(vec![meta(0, 2, 0), meta(1, 2, 1), leaf(2, &[])]);
+        assert_eq!(read_entries(&data).unwrap(), Vec::<&[u8]>::new());
+
+        // Truncated page
+        let mut data = db(vec![
+            meta(0, 2, 0),
+            meta(1, 2, 1),
+            leaf(2, &[(BUCKET_LEAF, HISTORY_BUCKET, &bucket_header(3))]),
+        ]);
+        data.truncate(PAGE_SIZE * 3);
+        assert!(read_entries(&data).is_err());
+
+        // Positions and sizes past the end of the page
+        for field in [4, 8, 12] {
+            let mut data = db(vec![
+                meta(0, 2, 0),
+                meta(1, 2, 1),
+                leaf(2, &[(BUCKET_LEAF, HISTORY_BUCKET, &bucket_header(0))]),
+            ]);
+            let offset = PAGE_SIZE * 2 + PAGE_HEADER_SIZE + field;
+            data[offset..offset + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+            assert!(read_entries(&data).is_err(), "{field}");
+        }
+
+        // Overflow count and page ID too large for the file
+        let mut data = db(vec![meta(0, 2, 0), meta(1, 2, 1), leaf(2, &[])]);
+        data[PAGE_SIZE * 2 + 12..PAGE_SIZE * 2 + 16].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(read_entries(&data).is_err());
+        let data = db(vec![meta(0, u64::MAX, 0), meta(1, u64::MAX, 1)]);
+        assert!(read_entries(&data).is_err());
+    }
+}
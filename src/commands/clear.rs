@@ -2,11 +2,16 @@ use std::path::Path;
 
 use miette::Result;
 
-use crate::database::{init_db, queries::delete_all_entries};
+use crate::{
+    cli::ClearArgs,
+    database::{init_db, queries::delete_all_entries},
+};
 
 #[tracing::instrument(skip(path_db))]
-pub fn execute(path_db: &Path) -> Result<()> {
+pub fn execute(path_db: &Path, args: ClearArgs) -> Result<()> {
+    let ClearArgs { include_pinned } = args;
+
     let conn = &init_db(path_db)?;
-    delete_all_entries(conn)?;
+    delete_all_entries(conn, include_pinned)?;
     Ok(())
 }
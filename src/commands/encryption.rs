@@ -0,0 +1,50 @@
+use std::path::Path;
+
+use miette::{Result, miette};
+
+use crate::{
+    cli::{KeyArgs, RekeyArgs},
+    database::{
+        encryption::{decrypt_db, encrypt_db, read_secret, rekey_db},
+        init_db_without_encryption,
+    },
+};
+
+/// Read the secret from the key args, which is required for every encryption command.
+fn required_secret(key: &KeyArgs) -> Result<Vec<u8>> {
+    read_secret(key.key_file.as_deref(), key.passphrase.as_deref())?.ok_or_else(|| {
+        miette!(
+            help = "provide the key using --key-file, CLIPVAULT_KEY_FILE or CLIPVAULT_PASSPHRASE",
+            "no key was given"
+        )
+    })
+}
+
+#[tracing::instrument(skip_all)]
+pub fn encrypt(path_db: &Path, key: &KeyArgs) -> Result<()> {
+    let secret = required_secret(key)?;
+    let mut conn = init_db_without_encryption(path_db)?;
+    encrypt_db(&mut conn, &secret)
+}
+
+#[tracing::instrument(skip_all)]
+pub fn decrypt(path_db: &Path, key: &KeyArgs) -> Result<()> {
+    let secret = required_secret(key)?;
+    let mut conn = init_db_without_encryption(path_db)?;
+    decrypt_db(&mut conn, &secret)
+}
+
+#[tracing::instrument(skip_all)]
+pub fn rekey(path_db: &Path, key: &KeyArgs, args: RekeyArgs) -> Result<()> {
+    let RekeyArgs {
+        new_key_file,
+        new_passphrase,
+    } = args;
+
+    let secret = required_secret(key)?;
+    let new_secret = read_secret(new_key_file.as_deref(), new_passphrase.as_deref())?
+        .ok_or_else(|| miette!("no new key was given"))?;
+
+    let mut conn = init_db_without_encryption(path_db)?;
+    rekey_db(&mut conn, &secret, &new_secret)
+}
@@ -0,0 +1,19 @@
+use std::path::Path;
+
+use miette::Result;
+
+use super::extract_id;
+use crate::{
+    cli::ExpireArgs,
+    database::{init_db, queries::set_expiry_by_id},
+    utils::now,
+};
+
+#[tracing::instrument(skip(path_db))]
+pub fn execute(path_db: &Path, args: ExpireArgs) -> Result<()> {
+    let ExpireArgs { input, duration } = args;
+
+    let id = extract_id(input)?;
+    let conn = init_db(path_db)?;
+    set_expiry_by_id(&conn, id, Some(now() + duration.as_secs()))
+}
@@ -0,0 +1,103 @@
+use std::{
+    fs::{File, create_dir_all},
+    io::{BufWriter, Write, stdout},
+    path::Path,
+};
+
+use miette::{Context, IntoDiagnostic, Result, miette};
+use rusqlite::Connection;
+use serde::Serialize;
+
+use super::list::entry_filter;
+use crate::{
+    archive::{Content, DIR_INDEX, Entry, Header},
+    cli::{ExportArgs, ExportFormat},
+    database::{data::EntryFilter, init_db, queries::for_each_full_entry},
+};
+
+/// Write the value as a single line of JSON.
+fn write_line(writer: &mut impl Write, value: &impl Serialize) -> Result<()> {
+    serde_json::to_writer(&mut *writer, value)
+        .into_diagnostic()
+        .context("failed to serialize entry")?;
+    writer
+        .write_all(b"\n")
+        .into_diagnostic()
+        .context("failed to write entry")
+}
+
+/// Write the header and every entry matching the filter, using the given function to decide where
+/// to put the content of each entry. The function is given the ID of the entry, its content, and
+/// the index of the part, or `None` for the main representation.
+fn write_archive(
+    conn: &Connection,
+    filter: &EntryFilter,
+    writer: impl Write,
+    mut content: impl FnMut(u64, Vec<u8>, Option<usize>) -> Result<Content>,
+) -> Result<()> {
+    let mut writer = BufWriter::new(writer);
+    write_line(&mut writer, &Header::default())?;
+
+    let mut count = 0;
+    for_each_full_entry(conn, filter, |full| {
+        let id = full.entry.id;
+        let entry = Entry::new(full, |c, part| content(id, c, part))?;
+        count += 1;
+        write_line(&mut writer, &entry)
+    })?;
+    tracing::debug!("exported {count} entries");
+
+    // Unlike `list`, a closed pipe is an error here, as the export would be incomplete
+    writer
+        .flush()
+        .into_diagnostic()
+        .context("failed to write export")
+}
+
+/// Write the content of each entry to a separate file in the directory, next to the index.
+fn export_dir(conn: &Connection, filter: &EntryFilter, dir: &Path) -> Result<()> {
+    create_dir_all(dir)
+        .into_diagnostic()
+        .context("failed to create export directory")?;
+
+    let index = File::create_new(dir.join(DIR_INDEX))
+        .into_diagnostic()
+        .with_context(|| format!("failed to create {DIR_INDEX} - is the directory empty?"))?;
+
+    write_archive(conn, filter, index, |id, content, part| {
+        let name = match part {
+            None => id.to_string(),
+            Some(i) => format!("{id}-{}", i + 1),
+        };
+        std::fs::write(dir.join(&name), content)
+            .into_diagnostic()
+            .with_context(|| format!("failed to write content of entry {id}"))?;
+
+        Ok(Content::File(name.into()))
+    })
+}
+
+#[tracing::instrument(skip(path_db))]
+pub fn execute(path_db: &Path, args: ExportArgs) -> Result<()> {
+    let ExportArgs {
+        format,
+        output,
+        filter,
+    } = args;
+
+    let filter = entry_filter(filter, false)?;
+    let conn = init_db(path_db)?;
+
+    let inline = |_, content, _| Ok(Content::Inline(content));
+    match (format, output) {
+        (ExportFormat::Ndjson, None) => write_archive(&conn, &filter, stdout().lock(), inline),
+        (ExportFormat::Ndjson, Some(path)) => {
+            let file = File::create(&path)
+                .into_diagnostic()
+                .context("failed to create export file")?;
+            write_archive(&conn, &filter, file, inline)
+        }
+        (ExportFormat::Dir, Some(dir)) => export_dir(&conn, &filter, &dir),
+        (ExportFormat::Dir, None) => Err(miette!("an output directory is required")),
+    }
+}
@@ -5,19 +5,23 @@ use std::{
 
 use miette::{Context, IntoDiagnostic, Result, miette};
 
-use super::extract_id;
+use super::{extract_id, extract_snippet_name};
 use crate::{
-    cli::GetDelArgs,
+    cli::{GetArgs, GetDelArgs},
     commands::wrap_index,
     database::{
         data::ClipboardEntry,
-        init_db,
-        queries::{count_entries, get_entry_by_id, get_entry_by_position},
+        get_db_connection, init_db,
+        queries::{
+            count_entries, get_entry_by_id, get_entry_by_position, get_entry_part,
+            get_entry_part_mime_types, get_snippet,
+        },
     },
-    utils::ignore_broken_pipe,
+    utils::{detect_mime_type, ignore_broken_pipe},
 };
 
-fn get_entry(path_db: &Path, mut input: String) -> Result<ClipboardEntry> {
+/// Get the entry selected by the input, and whether it is a snippet.
+fn get_entry(path_db: &Path, mut input: String) -> Result<(ClipboardEntry, bool)> {
     // Read from STDIN if no argument given
     if input.is_empty() {
         stdin()
@@ -27,9 +31,14 @@ fn get_entry(path_db: &Path, mut input: String) -> Result<ClipboardEntry> {
             .context("failed to read STDIN")?;
     }
 
+    if let Some(name) = extract_snippet_name(&input) {
+        let conn = init_db(path_db)?;
+        return get_snippet(&conn, name).map(|snippet| (snippet, true));
+    }
+
     let id = extract_id(input)?;
     let conn = init_db(path_db)?;
-    get_entry_by_id(&conn, id)
+    get_entry_by_id(&conn, id).map(|entry| (entry, false))
 }
 
 fn get_entry_rel(path_db: &Path, i: isize) -> Result<ClipboardEntry> {
@@ -45,8 +54,13 @@ fn get_entry_rel(path_db: &Path, i: isize) -> Result<ClipboardEntry> {
 }
 
 #[tracing::instrument(skip(path_db))]
-fn execute_inner(path_db: &Path, args: GetDelArgs, show_output: bool) -> Result<()> {
-    let GetDelArgs { input, index } = args;
+fn execute_inner(path_db: &Path, args: GetArgs, show_output: bool) -> Result<()> {
+    let GetArgs {
+        entry: GetDelArgs { input, index },
+        print_mime_type,
+        mime,
+        list_mime_types,
+    } = args;
 
     assert!(
         index.is_none() || index.is_some_and(|_| input.is_empty()),
@@ -54,8 +68,8 @@ fn execute_inner(path_db: &Path, args: GetDelArgs, show_output: bool) -> Result<
     );
 
     // Use relative index if given, otherwise parse the input for the entry ID
-    let entry = if let Some(i) = index {
-        get_entry_rel(path_db, i)
+    let (entry, is_snippet) = if let Some(i) = index {
+        get_entry_rel(path_db, i).map(|entry| (entry, false))
     } else {
         get_entry(path_db, input)
     }?;
@@ -65,11 +79,40 @@ fn execute_inner(path_db: &Path, args: GetDelArgs, show_output: bool) -> Result<
         return Ok(());
     }
 
+    // Entries stored before MIME types were recorded
+    let mime_type = entry
+        .mime_type
+        .unwrap_or_else(|| detect_mime_type(&entry.content));
+
+    let output = if print_mime_type {
+        format!("{mime_type}\n").into_bytes()
+    } else if list_mime_types {
+        let mut mime_types = vec![mime_type];
+        // Snippets only have a single representation
+        if !is_snippet {
+            let conn = get_db_connection(path_db)?;
+            mime_types.extend(get_entry_part_mime_types(&conn, entry.id)?);
+        }
+        format!("{}\n", mime_types.join("\n")).into_bytes()
+    } else if let Some(mime) = mime
+        && mime != mime_type
+    {
+        let part = if is_snippet {
+            None
+        } else {
+            let conn = get_db_connection(path_db)?;
+            get_entry_part(&conn, entry.id, &mime)?
+        };
+        part.ok_or_else(|| miette!("entry has no representation with MIME type: {mime}"))?
+    } else {
+        entry.content
+    };
+
     // Write to STDOUT
     let stdout = stdout();
     let mut stdout = stdout.lock();
 
-    ignore_broken_pipe(stdout.write_all(&entry.content))
+    ignore_broken_pipe(stdout.write_all(&output))
         .into_diagnostic()
         .context("failed to write to STDOUT")?;
     ignore_broken_pipe(stdout.flush())
@@ -80,13 +123,13 @@ fn execute_inner(path_db: &Path, args: GetDelArgs, show_output: bool) -> Result<
 }
 
 #[tracing::instrument(skip(path_db))]
-pub fn execute(path_db: &Path, args: GetDelArgs) -> Result<()> {
+pub fn execute(path_db: &Path, args: GetArgs) -> Result<()> {
     execute_inner(path_db, args, true)
 }
 
 #[doc(hidden)]
 #[tracing::instrument(skip(path_db))]
-pub fn execute_without_output(path_db: &Path, args: GetDelArgs) -> Result<()> {
+pub fn execute_without_output(path_db: &Path, args: GetArgs) -> Result<()> {
     assert!(
         !cfg!(debug_assertions),
         "Not intended to run in production code"
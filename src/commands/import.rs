@@ -0,0 +1,265 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, stdin},
+    path::{Component, Path},
+    time::UNIX_EPOCH,
+};
+
+use miette::{Context, IntoDiagnostic, Result, miette};
+use rusqlite::Connection;
+
+use super::store::{is_ignored, is_ignored_by};
+use crate::{
+    archive::{Content, DIR_INDEX, Entry, FORMAT, Header, VERSION},
+    cli::ImportArgs,
+    cliphist::{is_cliphist_db, read_entries},
+    database::{
+        data::{ClipboardEntry, FullEntry},
+        init_db,
+        queries::{
+            add_entry_tag, content_exists, replace_entry_parts, set_pinned_by_id, upsert_entry_at,
+        },
+        write_transaction,
+    },
+    utils::{detect_mime_type, now},
+};
+
+/// What happened to the entries which were read.
+#[derive(Debug, Default)]
+struct Summary {
+    /// Added as new entries.
+    imported: usize,
+    /// Merged into existing entries with the same content.
+    merged: usize,
+    /// Not imported due to the size limits or ignore options.
+    skipped: usize,
+}
+
+/// Add the entry to the database, unless it is filtered out.
+fn import_entry(
+    conn: &Connection,
+    args: &ImportArgs,
+    full: FullEntry,
+    summary: &mut Summary,
+) -> Result<()> {
+    let FullEntry {
+        entry:
+            ClipboardEntry {
+                content,
+                last_updated,
+                mime_type,
+                ..
+            },
+        pinned,
+        expires_at,
+        tags,
+        mut parts,
+    } = full;
+    let max_bytes = args.max_entry_length;
+
+    if is_ignored(
+        &content,
+        args.min_entry_length,
+        max_bytes,
+        args.ignore_pattern.as_deref(),
+    ) {
+        summary.skipped += 1;
+        return Ok(());
+    }
+
+    let mime_type = mime_type.unwrap_or_else(|| detect_mime_type(&content));
+    if is_ignored_by(&content, &mime_type, &args.ignore) {
+        summary.skipped += 1;
+        return Ok(());
+    }
+    parts.retain(|part| {
+        part.mime_type != mime_type
+            && !part.content.is_empty()
+            && (part.content.len() <= max_bytes || max_bytes == 0)
+    });
+
+    let exists = content_exists(conn, &content)?;
+    let id = upsert_entry_at(conn, &content, &mime_type, last_updated, expires_at)?;
+    if !parts.is_empty() {
+        replace_entry_parts(conn, id, &parts)?;
+    }
+    for tag in &tags {
+        add_entry_tag(conn, id, tag)?;
+    }
+    if pinned {
+        set_pinned_by_id(conn, id, true)?;
+    }
+
+    if exists {
+        summary.merged += 1;
+    } else {
+        summary.imported += 1;
+    }
+
+    Ok(())
+}
+
+/// Import every entry from an export, reading the content of entries from files relative to the
+/// given directory if needed.
+fn import_archive(
+    conn: &Connection,
+    args: &ImportArgs,
+    reader: impl BufRead,
+    dir: &Path,
+    summary: &mut Summary,
+) -> Result<()> {
+    let mut lines = reader.lines();
+
+    let header = lines
+        .next()
+        .transpose()
+        .into_diagnostic()
+        .context("failed to read export")?
+        .and_then(|line| serde_json::from_str::<Header>(&line).ok())
+        .filter(|header| header.format == FORMAT)
+        .ok_or_else(|| miette!("not a clipvault export or cliphist database"))?;
+    if header.version > VERSION {
+        return Err(miette!(
+            help = "update clipvault to import it",
+            "unsupported export version: {}",
+            header.version
+        ));
+    }
+
+    for (i, line) in lines.enumerate() {
+        // The header is on the first line
+        let line_number = i + 2;
+        let line = line.into_diagnostic().context("failed to read export")?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let entry = serde_json::from_str::<Entry>(&line)
+            .into_diagnostic()
+            .with_context(|| format!("invalid entry on line {line_number}"))?
+            .into_full(|content| match content {
+                Content::Inline(content) => Ok(content),
+                // Exports only refer to files inside their own directory, so anything else is
+                // treated as malicious rather than read into the history
+                Content::File(path)
+                    if !path.components().all(|c| matches!(c, Component::Normal(_))) =>
+                {
+                    Err(miette!(
+                        "invalid path for the content of the entry on line {line_number}: {}",
+                        path.display()
+                    ))
+                }
+                Content::File(path) => std::fs::read(dir.join(&path))
+                    .into_diagnostic()
+                    .with_context(|| {
+                        format!(
+                            "failed to read content of the entry on line {line_number}: {}",
+                            path.display()
+                        )
+                    }),
+            })?;
+
+        import_entry(conn, args, entry, summary)?;
+    }
+
+    Ok(())
+}
+
+/// Import every entry from a cliphist database.
+///
+/// cliphist doesn't record when entries were copied, so the newest entry is given the time the
+/// database was last modified, and each older entry a second less to keep them in order.
+fn import_cliphist(
+    conn: &Connection,
+    args: &ImportArgs,
+    path: &Path,
+    summary: &mut Summary,
+) -> Result<()> {
+    let data = std::fs::read(path)
+        .into_diagnostic()
+        .context("failed to read cliphist database")?;
+    let modified = path
+        .metadata()
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map_or_else(now, |d| d.as_secs());
+
+    let entries = read_entries(&data)?;
+    let count = entries.len() as u64;
+    tracing::debug!("found {count} cliphist entries");
+
+    for (i, content) in (0..).zip(entries) {
+        let entry = FullEntry {
+            entry: ClipboardEntry {
+                id: 0,
+                content: content.to_vec(),
+                last_updated: modified.saturating_sub(count - 1 - i),
+                mime_type: None,
+            },
+            pinned: false,
+            expires_at: None,
+            tags: Vec::new(),
+            parts: Vec::new(),
+        };
+        import_entry(conn, args, entry, summary)?;
+    }
+
+    Ok(())
+}
+
+#[tracing::instrument(skip(path_db))]
+pub fn execute(path_db: &Path, args: ImportArgs) -> Result<()> {
+    if args.min_entry_length > args.max_entry_length && args.max_entry_length != 0 {
+        return Err(miette!(
+            "minimum entry length ({}) exceeds maximum entry length ({})",
+            args.min_entry_length,
+            args.max_entry_length
+        ));
+    }
+
+    let mut conn = init_db(path_db)?;
+    let mut summary = Summary::default();
+
+    // A failed import leaves the history untouched
+    let tx = write_transaction(&mut conn)?;
+    match &args.path {
+        None => import_archive(&tx, &args, stdin().lock(), Path::new(""), &mut summary)?,
+        Some(path) if path.is_dir() => {
+            let index = File::open(path.join(DIR_INDEX))
+                .into_diagnostic()
+                .with_context(|| format!("failed to open {DIR_INDEX} in the directory"))?;
+            import_archive(&tx, &args, BufReader::new(index), path, &mut summary)?;
+        }
+        Some(path) => {
+            let mut reader = BufReader::new(
+                File::open(path)
+                    .into_diagnostic()
+                    .context("failed to open file to import")?,
+            );
+            let start = reader
+                .fill_buf()
+                .into_diagnostic()
+                .context("failed to read file to import")?;
+
+            if is_cliphist_db(start) {
+                import_cliphist(&tx, &args, path, &mut summary)?;
+            } else {
+                let dir = path.parent().unwrap_or(Path::new(""));
+                import_archive(&tx, &args, reader, dir, &mut summary)?;
+            }
+        }
+    }
+    tx.commit()
+        .into_diagnostic()
+        .context("failed to commit transaction")?;
+
+    let Summary {
+        imported,
+        merged,
+        skipped,
+    } = summary;
+    println!("imported {imported}, merged {merged}, skipped {skipped}");
+
+    Ok(())
+}
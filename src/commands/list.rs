@@ -1,84 +1,268 @@
 use std::{
-    io::{Cursor, Write, stdout},
-    path::Path,
+    collections::HashMap,
+    io::{Write, stdout},
+    path::{Path, PathBuf},
 };
 
-use content_inspector::ContentType;
-use image::{GenericImageView, ImageReader};
-use miette::{Context, IntoDiagnostic, Result};
-use mime_sniffer::MimeTypeSniffer;
+use miette::{Context, IntoDiagnostic, Result, miette};
+use rusqlite::Connection;
+use serde::Serialize;
 
-use super::SEPARATOR;
+use super::{SEPARATOR, SNIPPET_PREFIX, thumbnail::sync_thumbnails};
 
 use crate::{
-    cli::ListArgs,
-    database::{init_db, queries::get_all_entries},
-    utils::{human_bytes, ignore_broken_pipe, truncate},
+    cli::{FilterArgs, ListArgs, ListFormat},
+    database::{
+        data::{EntryFilter, EntryKind, EntrySummary},
+        init_db,
+        queries::{get_entries, get_snippets, is_encrypted},
+    },
+    template::{Placeholder, Segment, Template},
+    utils::{UNKNOWN_MIME_TYPE, human_age, human_bytes, ignore_broken_pipe, now, truncate},
 };
 
-fn preview_image(data: &[u8]) -> Option<String> {
-    let img_reader = ImageReader::new(Cursor::new(data))
-        .with_guessed_format()
-        .ok()?;
-    let format = img_reader.format()?;
-    let img = img_reader.decode().ok()?;
-    let (width, height) = img.dimensions();
-
-    Some(format!(
-        "[[ binary data {} {} {width}x{height} ]]",
-        human_bytes(data.len()),
-        format.to_mime_type(),
-    ))
+/// Human-readable preview of an entry, truncated to the given width.
+#[tracing::instrument(skip(entry))]
+pub fn preview(entry: &EntrySummary, width: usize) -> String {
+    let EntrySummary {
+        mime_type,
+        metadata,
+        ..
+    } = entry;
+
+    match metadata.kind {
+        // More details for image types
+        EntryKind::Image => {
+            let (width, height) = metadata.dimensions.unwrap_or_default();
+            format!(
+                "[[ binary data {} {} {width}x{height} ]]",
+                human_bytes(metadata.size),
+                mime_type.as_deref().unwrap_or_default(),
+            )
+        }
+        // Include the MIME type for other binary data, if known
+        EntryKind::Binary => match mime_type.as_deref() {
+            Some(mimetype) if mimetype != UNKNOWN_MIME_TYPE => {
+                format!("[[ binary data {mimetype}]]")
+            }
+            _ => "[[ binary data ]]".into(),
+        },
+        EntryKind::Text => truncate(metadata.preview.as_deref().unwrap_or_default(), width).into(),
+        EntryKind::NonUtf8Text => "[[ Non-UTF-8 text ]]".into(),
+    }
 }
 
-fn get_mimemtype(data: &[u8]) -> Option<String> {
-    data.sniff_mime_type().map(String::from)
+/// ID of an entry as used in plain output, or the prefixed name for snippets.
+fn entry_id(entry: &EntrySummary) -> String {
+    match &entry.snippet {
+        Some(name) => format!("{SNIPPET_PREFIX}{name}"),
+        None => entry.id.to_string(),
+    }
 }
 
-fn preview_text(data: &[u8], width: usize) -> String {
-    let mut result = String::with_capacity(data.len());
-    String::from_utf8_lossy(data)
-        .split_whitespace()
-        .for_each(|w| {
-            if !result.is_empty() {
-                result.push(' ');
-            }
-            result.push_str(w);
-        });
+/// Entry as represented in the structured output formats.
+#[derive(Debug, Serialize)]
+struct JsonEntry<'a> {
+    /// `None` for snippets.
+    id: Option<u64>,
+    snippet: Option<&'a str>,
+    last_updated: u64,
+    size: usize,
+    kind: &'static str,
+    mime_type: Option<&'a str>,
+    pinned: bool,
+    tags: &'a [String],
+    /// Hex-encoded hash of the content, to detect identical entries. `None` for snippets.
+    hash: Option<&'a str>,
+    width: Option<u32>,
+    height: Option<u32>,
+    preview: String,
+}
 
-    truncate(&result, width).into_owned()
+impl<'a> JsonEntry<'a> {
+    fn new(entry: &'a EntrySummary, width: usize) -> Self {
+        let (img_width, img_height) = entry.metadata.dimensions.unzip();
+
+        Self {
+            id: entry.snippet.is_none().then_some(entry.id),
+            snippet: entry.snippet.as_deref(),
+            last_updated: entry.last_updated,
+            size: entry.metadata.size,
+            kind: entry.metadata.kind.as_str(),
+            mime_type: entry.mime_type.as_deref(),
+            pinned: entry.pinned,
+            tags: &entry.tags,
+            hash: entry.hash.as_deref(),
+            width: img_width,
+            height: img_height,
+            preview: preview(entry, width),
+        }
+    }
 }
 
-#[tracing::instrument(skip(data))]
-fn preview(id: u64, data: &[u8], width: usize) -> String {
-    let data_type = content_inspector::inspect(data);
-    let s = match data_type {
-        ContentType::BINARY => {
-            // More details for image types
-            if let Some(img_msg) = preview_image(data) {
-                img_msg
-            }
-            // Try and parse mime-type for other binary data
-            else if let Some(mimetype) = get_mimemtype(data) {
-                format!("[[ binary data {mimetype}]]")
-            } else {
-                "[[ binary data ]]".into()
+/// Format an entry using the given template.
+fn render(
+    template: &Template,
+    entry: &EntrySummary,
+    width: usize,
+    now: u64,
+    thumbnails: &HashMap<u64, PathBuf>,
+) -> String {
+    let mut line = String::new();
+
+    for segment in template.segments() {
+        match segment {
+            Segment::Literal(s) => line.push_str(s),
+            Segment::Placeholder(placeholder) => {
+                let value = match placeholder {
+                    Placeholder::Id => entry_id(entry),
+                    Placeholder::Preview => preview(entry, width),
+                    Placeholder::Mime => entry.mime_type.clone().unwrap_or_default(),
+                    Placeholder::Kind => entry.metadata.kind.as_str().into(),
+                    Placeholder::Size => human_bytes(entry.metadata.size),
+                    Placeholder::Width => entry
+                        .metadata
+                        .dimensions
+                        .map(|(w, _)| w.to_string())
+                        .unwrap_or_default(),
+                    Placeholder::Height => entry
+                        .metadata
+                        .dimensions
+                        .map(|(_, h)| h.to_string())
+                        .unwrap_or_default(),
+                    Placeholder::Age => human_age(now.saturating_sub(entry.last_updated)),
+                    Placeholder::LastUpdated => entry.last_updated.to_string(),
+                    Placeholder::Pinned => if entry.pinned { "*" } else { "" }.into(),
+                    Placeholder::Tags => entry.tags.join(","),
+                    // Thumbnails are only generated for entries in the history
+                    Placeholder::Thumbnail => thumbnails
+                        .get(&entry.id)
+                        .filter(|_| entry.snippet.is_none())
+                        .map(|path| path.display().to_string())
+                        .unwrap_or_default(),
+                };
+                line.push_str(&value);
             }
         }
-        ContentType::UTF_8 | ContentType::UTF_8_BOM => preview_text(data, width),
-        _ => "[[ Non-UTF-8 text ]]".into(),
+    }
+
+    line
+}
+
+/// Format the given entries for output, based on the chosen format.
+fn format_entries(
+    entries: &[EntrySummary],
+    format: ListFormat,
+    template: Option<&Template>,
+    width: usize,
+    thumbnails: &HashMap<u64, PathBuf>,
+) -> Result<String> {
+    let output = match (format, template) {
+        (ListFormat::Plain, Some(template)) => {
+            let now = now();
+            entries
+                .iter()
+                .map(|entry| render(template, entry, width, now, thumbnails))
+                .collect::<Vec<_>>()
+                .join("\n")
+        }
+        (ListFormat::Plain, None) => entries
+            .iter()
+            .map(|entry| format!("{}{SEPARATOR}{}", entry_id(entry), preview(entry, width)))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        (ListFormat::Json, _) => {
+            let entries = entries
+                .iter()
+                .map(|entry| JsonEntry::new(entry, width))
+                .collect::<Vec<_>>();
+            serde_json::to_string(&entries)
+                .into_diagnostic()
+                .context("failed to serialize entries")?
+        }
+        (ListFormat::Ndjson, _) => entries
+            .iter()
+            .map(|entry| serde_json::to_string(&JsonEntry::new(entry, width)))
+            .collect::<Result<Vec<_>, _>>()
+            .into_diagnostic()
+            .context("failed to serialize entries")?
+            .join("\n"),
     };
 
-    format!("{id}{SEPARATOR}{s}")
+    Ok(output)
 }
 
-#[tracing::instrument(skip(path_db))]
-fn execute_inner(path_db: &Path, args: ListArgs, show_output: bool) -> Result<()> {
+/// Convert the filter args into absolute values which can be used by queries.
+pub(super) fn entry_filter(args: FilterArgs, pinned_first: bool) -> Result<EntryFilter> {
+    let FilterArgs {
+        pinned,
+        tag,
+        kind,
+        mime,
+        min_size,
+        max_size,
+        since,
+        until,
+        limit,
+        offset,
+    } = args;
+
+    if let (Some(min), Some(max)) = (min_size, max_size)
+        && min > max
+    {
+        return Err(miette!("minimum size ({min}) exceeds maximum size ({max})"));
+    }
+    if let (Some(since), Some(until)) = (since, until)
+        && until.as_secs() > since.as_secs()
+    {
+        return Err(miette!(
+            "`--until` ({until}) must not be longer ago than `--since` ({since})"
+        ));
+    }
+
+    let now = now();
+    Ok(EntryFilter {
+        pinned,
+        tag,
+        pinned_first,
+        kind,
+        mime_type: mime,
+        min_size,
+        max_size,
+        since: since.map(|d| now.saturating_sub(d.as_secs())),
+        until: until.map(|d| now.saturating_sub(d.as_secs())),
+        limit,
+        offset,
+    })
+}
+
+/// Write the entries returned by the given function to STDOUT, formatted based on the args.
+#[tracing::instrument(skip(path_db, get_entries))]
+pub(super) fn output_entries(
+    path_db: &Path,
+    args: ListArgs,
+    get_entries: impl FnOnce(&Connection, &EntryFilter) -> Result<Vec<EntrySummary>>,
+    show_output: bool,
+) -> Result<()> {
     let ListArgs {
         max_preview_width,
         reverse,
+        pinned_first,
+        snippets,
+        format,
+        template,
+        filter,
+        thumbnails: thumbnail_args,
     } = args;
 
+    let filter = entry_filter(filter, pinned_first)?;
+
+    if template.is_some() && format != ListFormat::Plain {
+        return Err(miette!(
+            "a template can only be used with the plain output format"
+        ));
+    }
+
     let preview_width = if max_preview_width == 0 {
         tracing::debug!("preview width limit disabled");
         usize::MAX
@@ -86,28 +270,52 @@ fn execute_inner(path_db: &Path, args: ListArgs, show_output: bool) -> Result<()
         max_preview_width
     };
 
-    // Database only needed to get the entries - avoid locking
-    let entries = {
+    // Database only needed to get the entries and thumbnails - avoid locking
+    let (entries, thumbnails) = {
         let conn = init_db(path_db)?;
-        let mut entries = get_all_entries(&conn, preview_width)?;
+        let mut history = get_entries(&conn, &filter)?;
         if reverse {
-            entries.reverse();
+            history.reverse();
         }
+        // Snippets stay first, whatever the order of the history
+        let mut entries = if snippets {
+            get_snippets(&conn)?
+        } else {
+            Vec::new()
+        };
+        entries.extend(history);
+
+        let thumbnails = match &template {
+            // Leave the placeholder empty rather than failing, so that pickers keep working
+            Some(t) if t.contains(Placeholder::Thumbnail) && is_encrypted(&conn)? => {
+                tracing::warn!("thumbnails are not available for encrypted databases");
+                HashMap::new()
+            }
+            Some(t) if t.contains(Placeholder::Thumbnail) => {
+                sync_thumbnails(&conn, &thumbnail_args)?
+                    .into_iter()
+                    .collect()
+            }
+            _ => HashMap::new(),
+        };
 
-        entries
+        (entries, thumbnails)
     };
     tracing::debug!("entries count: {}", entries.len());
 
-    if entries.is_empty() {
+    // An empty JSON array is still valid output
+    if entries.is_empty() && format != ListFormat::Json {
         return Ok(());
     }
 
     // Combine previews into a single string so that all the output can be written to STDOUT at the same time
-    let output = entries
-        .into_iter()
-        .map(|entry| preview(entry.id, &entry.content, preview_width))
-        .collect::<Vec<_>>()
-        .join("\n");
+    let output = format_entries(
+        &entries,
+        format,
+        template.as_ref(),
+        preview_width,
+        &thumbnails,
+    )?;
 
     // Used for benchmarks - don't actually write to stdout
     if !show_output {
@@ -125,6 +333,11 @@ fn execute_inner(path_db: &Path, args: ListArgs, show_output: bool) -> Result<()
     Ok(())
 }
 
+#[tracing::instrument(skip(path_db))]
+fn execute_inner(path_db: &Path, args: ListArgs, show_output: bool) -> Result<()> {
+    output_entries(path_db, args, get_entries, show_output)
+}
+
 #[tracing::instrument(skip(path_db))]
 pub fn execute(path_db: &Path, args: ListArgs) -> Result<()> {
     execute_inner(path_db, args, true)
@@ -0,0 +1,67 @@
+use std::path::Path;
+
+use miette::{Context, IntoDiagnostic, Result, miette};
+
+use super::store::prune;
+use crate::{
+    cli::MergeArgs,
+    database::{
+        attach_merge_source,
+        data::PruneOptions,
+        detach_merge_source, init_db,
+        queries::{count_attached_entries, count_entries, merge_attached_entries},
+        write_transaction,
+    },
+};
+
+#[tracing::instrument(skip(path_db))]
+pub fn execute(path_db: &Path, args: MergeArgs) -> Result<()> {
+    let MergeArgs {
+        from,
+        max_entries,
+        max_entry_age,
+        prune_pinned,
+        keep_tagged,
+    } = args;
+
+    // Attaching a missing file would create an empty database
+    if !from.is_file() {
+        return Err(miette!("database to merge not found: {}", from.display()));
+    }
+
+    let mut conn = init_db(path_db)?;
+
+    let is_same = path_db
+        .canonicalize()
+        .and_then(|db| Ok(db == from.canonicalize()?))
+        .into_diagnostic()
+        .context("failed to resolve database paths")?;
+    if is_same {
+        return Err(miette!("can't merge a database into itself"));
+    }
+
+    let source = attach_merge_source(&conn, &from)?;
+
+    // Either every entry is merged and the history pruned, or nothing changes
+    let tx = write_transaction(&mut conn)?;
+    let before = count_entries(&tx)?;
+    let total = count_attached_entries(&tx)?;
+
+    merge_attached_entries(&tx)?;
+    let added = count_entries(&tx)? - before;
+
+    let prune_options = PruneOptions {
+        include_pinned: prune_pinned,
+        keep_tagged,
+    };
+    let pruned = prune(&tx, max_entries, max_entry_age, prune_options)?;
+
+    tx.commit()
+        .into_diagnostic()
+        .context("failed to commit transaction")?;
+    detach_merge_source(&conn, source)?;
+
+    println!("added {added}, merged {}, pruned {pruned}", total - added);
+
+    Ok(())
+}
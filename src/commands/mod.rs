@@ -1,14 +1,37 @@
 pub mod clear;
 pub mod delete;
+pub mod encryption;
+pub mod expire;
+pub mod export;
 pub mod get;
+pub mod import;
 pub mod list;
+pub mod merge;
+pub mod pause;
+pub mod pin;
+pub mod rules;
+pub mod search;
+pub mod snippet;
 pub mod store;
+pub mod tag;
+pub mod thumbnail;
+pub mod vacuum;
 
-use miette::{Context, IntoDiagnostic, Result};
+use miette::{Context, IntoDiagnostic, Result, miette};
 
 pub(super) const SEPARATOR: &str = "\t";
 
+/// Prefix for the names of snippets in place of an ID, e.g. `:name`.
+pub(super) const SNIPPET_PREFIX: char = ':';
+
 pub(super) fn extract_id(input: String) -> Result<u64> {
+    if extract_snippet_name(&input).is_some() {
+        return Err(miette!(
+            help = "use `clipvault snippet` to manage snippets",
+            "expected an entry, not a snippet"
+        ));
+    }
+
     let str = match input.split_once(SEPARATOR) {
         Some((s, _)) => s,
         None => input.trim(),
@@ -16,6 +39,16 @@ pub(super) fn extract_id(input: String) -> Result<u64> {
     str.parse().into_diagnostic().context("failed to parse ID")
 }
 
+/// Get the name of the snippet if the input refers to one, e.g. `:name` as output by
+/// `clipvault list --snippets`.
+pub(super) fn extract_snippet_name(input: &str) -> Option<&str> {
+    let str = match input.split_once(SEPARATOR) {
+        Some((s, _)) => s,
+        None => input.trim(),
+    };
+    str.strip_prefix(SNIPPET_PREFIX).filter(|s| !s.is_empty())
+}
+
 pub(super) fn wrap_index(len: usize, index: isize) -> usize {
     assert!(len > 0);
 
@@ -62,6 +95,19 @@ mod test {
         assert_eq!(extract_id("17\t15".into()).unwrap(), 17);
     }
 
+    #[test]
+    fn test_extract_snippet_name() {
+        assert_eq!(extract_snippet_name(":ssh"), Some("ssh"));
+        assert_eq!(extract_snippet_name(":ssh\tssh prod"), Some("ssh"));
+        assert_eq!(extract_snippet_name(":a:b\n"), Some("a:b"));
+
+        assert_eq!(extract_snippet_name(":"), None);
+        assert_eq!(extract_snippet_name("7\t:ssh"), None);
+        assert_eq!(extract_snippet_name("ssh"), None);
+
+        assert!(extract_id(":ssh\tssh prod".into()).is_err());
+    }
+
     #[test]
     fn test_wrap_index() {
         // Only one item available
@@ -0,0 +1,47 @@
+use std::{
+    path::Path,
+    time::{Duration, UNIX_EPOCH},
+};
+
+use miette::Result;
+
+use crate::{
+    cli::PauseArgs,
+    database::{
+        data::Pause,
+        init_db_without_encryption,
+        queries::{delete_pause, get_pause, set_pause},
+    },
+    utils::now,
+};
+
+/// Pause capturing entries. Content is never read, so the key isn't needed.
+#[tracing::instrument(skip(path_db))]
+pub fn pause(path_db: &Path, args: PauseArgs) -> Result<()> {
+    let until = args.duration.map(|duration| now() + duration.as_secs());
+    set_pause(&init_db_without_encryption(path_db)?, Pause { until })
+}
+
+#[tracing::instrument(skip(path_db))]
+pub fn resume(path_db: &Path) -> Result<()> {
+    delete_pause(&init_db_without_encryption(path_db)?)
+}
+
+#[tracing::instrument(skip(path_db))]
+pub fn status(path_db: &Path) -> Result<()> {
+    match get_pause(&init_db_without_encryption(path_db)?)? {
+        None => println!("active"),
+        Some(Pause { until: None }) => println!("paused"),
+        Some(Pause { until: Some(until) }) => {
+            let time = UNIX_EPOCH + Duration::from_secs(until);
+            let left = Duration::from_secs(until.saturating_sub(now()));
+            println!(
+                "paused until {} ({} left)",
+                humantime::format_rfc3339_seconds(time),
+                humantime::format_duration(left)
+            );
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,71 @@
+use std::io::{Read, stdin};
+
+use miette::{Context, IntoDiagnostic, Result};
+
+use crate::{
+    cli::{RulesCommand, RulesTestArgs},
+    rules::{RuleAction, RuleInput, first_match, local_time},
+    utils::detect_mime_type,
+};
+
+#[tracing::instrument]
+pub fn execute(command: RulesCommand) -> Result<()> {
+    match command {
+        RulesCommand::Test(args) => test(args),
+    }
+}
+
+/// Print the rule which would apply to the content read from STDIN, and what it would do.
+fn test(args: RulesTestArgs) -> Result<()> {
+    let RulesTestArgs {
+        mime_type,
+        clipboard_state,
+        source,
+        at,
+        rules,
+    } = args;
+
+    let mut content = vec![];
+    stdin()
+        .read_to_end(&mut content)
+        .into_diagnostic()
+        .context("failed to read from STDIN")?;
+    let mime_type = mime_type.unwrap_or_else(|| detect_mime_type(&content));
+
+    let input = RuleInput::new(
+        &content,
+        &mime_type,
+        clipboard_state.as_deref(),
+        source.as_deref(),
+        at.unwrap_or_else(local_time),
+    );
+    let Some((i, rule)) = first_match(&rules, &input) else {
+        println!("no rule matched");
+        return Ok(());
+    };
+
+    println!("rule: {}", rule.name(i));
+    match rule.action {
+        RuleAction::Ignore => {
+            println!("action: ignore");
+            return Ok(());
+        }
+        RuleAction::Store => println!("action: store"),
+    }
+    if let Some(ttl) = rule.ttl {
+        println!("ttl: {ttl}");
+    }
+    if !rule.tags.is_empty() {
+        println!("tags: {}", rule.tags.join(","));
+    }
+    if rule.pin {
+        println!("pin: yes");
+    }
+
+    let transformed = rule.transform(content.clone());
+    if transformed != content {
+        println!("content: {}", String::from_utf8_lossy(&transformed));
+    }
+
+    Ok(())
+}
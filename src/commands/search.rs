@@ -0,0 +1,67 @@
+use std::path::Path;
+
+use miette::{Result, miette};
+
+use crate::{
+    cli::{ListArgs, SearchArgs, SearchOrder},
+    database::queries::{is_encrypted, search_entries},
+};
+
+/// Escape the given text so that it is matched as a single phrase by FTS5.
+fn literal_query(text: &str) -> String {
+    format!("\"{}\"", text.replace('"', "\"\""))
+}
+
+#[tracing::instrument(skip(path_db))]
+pub fn execute(path_db: &Path, args: SearchArgs) -> Result<()> {
+    let SearchArgs {
+        query,
+        literal,
+        order,
+        list,
+    } = args;
+
+    let query = if literal {
+        literal_query(&query)
+    } else {
+        query
+    };
+    tracing::debug!("query: {query}");
+
+    // Snippets are not indexed - avoid listing all of them, e.g. if enabled in the config file
+    let list = ListArgs {
+        snippets: false,
+        ..list
+    };
+
+    let by_rank = order == SearchOrder::Rank;
+    super::list::output_entries(
+        path_db,
+        list,
+        |conn, filter| {
+            // The content of encrypted entries is never indexed
+            if is_encrypted(conn)? {
+                return Err(miette!(
+                    help = "use `clipvault list` to filter entries instead",
+                    "full-text search is not available for encrypted databases"
+                ));
+            }
+            search_entries(conn, &query, filter, by_rank)
+        },
+        true,
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_literal_query() {
+        assert_eq!(literal_query("abc"), r#""abc""#);
+        assert_eq!(literal_query("foo-bar* baz"), r#""foo-bar* baz""#);
+        assert_eq!(literal_query(r#"say "hi""#), r#""say ""hi""""#);
+    }
+}
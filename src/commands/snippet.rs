@@ -0,0 +1,170 @@
+use std::{
+    io::{Read, Write, stdin, stdout},
+    path::Path,
+    process::Command,
+};
+
+use miette::{Context, IntoDiagnostic, Result, miette};
+
+use super::{SEPARATOR, extract_id, list::preview, wrap_index};
+use crate::{
+    cli::{SnippetAddArgs, SnippetArgs, SnippetCommand},
+    database::{
+        init_db,
+        queries::{
+            count_entries, delete_snippet, get_entry_by_id, get_entry_by_position, get_snippet,
+            get_snippets, upsert_snippet,
+        },
+    },
+    defaults,
+    utils::{detect_mime_type, ignore_broken_pipe},
+};
+
+fn write_stdout(output: &[u8]) -> Result<()> {
+    let mut stdout = stdout().lock();
+    ignore_broken_pipe(stdout.write_all(output))
+        .into_diagnostic()
+        .context("failed to write to STDOUT")?;
+    ignore_broken_pipe(stdout.flush())
+        .into_diagnostic()
+        .context("failed to flush STDOUT")
+}
+
+fn add(path_db: &Path, args: SnippetAddArgs) -> Result<()> {
+    let SnippetAddArgs {
+        name,
+        entry,
+        index,
+        mime_type,
+        force,
+    } = args;
+
+    // Copy an existing entry from the history, or read the content from STDIN
+    let (conn, content, mime_type) = if let Some(i) = index {
+        let conn = init_db(path_db)?;
+        let len = count_entries(&conn)?;
+        if len == 0 {
+            return Err(miette!("there are currently no saved clipboard entries"));
+        }
+
+        let entry = get_entry_by_position(&conn, wrap_index(len, i))?;
+        (conn, entry.content, entry.mime_type)
+    } else if let Some(input) = entry {
+        let id = extract_id(input)?;
+        let conn = init_db(path_db)?;
+        let entry = get_entry_by_id(&conn, id)?;
+        (conn, entry.content, entry.mime_type)
+    } else {
+        let mut buf = vec![];
+        stdin()
+            .lock()
+            .read_to_end(&mut buf)
+            .into_diagnostic()
+            .context("failed to read STDIN")?;
+        if buf.is_empty() {
+            return Err(miette!("no content given for the snippet"));
+        }
+
+        // Only get DB connection after reading STDIN - avoid locking
+        (init_db(path_db)?, buf, mime_type)
+    };
+
+    let mime_type = mime_type.unwrap_or_else(|| detect_mime_type(&content));
+    upsert_snippet(&conn, &name, &content, &mime_type, force)
+}
+
+fn list(path_db: &Path) -> Result<()> {
+    let snippets = get_snippets(&init_db(path_db)?)?;
+
+    let output = snippets
+        .iter()
+        .map(|snippet| {
+            format!(
+                "{}{SEPARATOR}{}\n",
+                snippet.snippet.as_deref().unwrap_or_default(),
+                preview(snippet, defaults::MAX_PREVIEW_WIDTH)
+            )
+        })
+        .collect::<String>();
+
+    write_stdout(output.as_bytes())
+}
+
+/// Open the text of the snippet in the user's editor, and save any changes.
+fn edit(path_db: &Path, name: &str) -> Result<()> {
+    let snippet = get_snippet(&init_db(path_db)?, name)?;
+    if std::str::from_utf8(&snippet.content).is_err() {
+        return Err(miette!("only text snippets can be edited"));
+    }
+
+    // Only readable by the user, and created anew so that it can't be a pre-existing symlink. It's
+    // deleted when dropped, so must be kept until the edited content has been read.
+    let mut file = tempfile::Builder::new()
+        .prefix("clipvault-snippet-")
+        .tempfile()
+        .into_diagnostic()
+        .context("failed to create temporary file")?;
+    let path = file.path().to_owned();
+    file.write_all(&snippet.content)
+        .and_then(|()| file.flush())
+        .into_diagnostic()
+        .with_context(|| format!("failed to write temporary file: {}", path.display()))?;
+
+    // Run through the shell, as the editor may include arguments, e.g. `code --wait`
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".into());
+    tracing::debug!("editor: {editor}");
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(format!("{editor} \"$1\""))
+        .arg("sh")
+        .arg(&path)
+        .status()
+        .into_diagnostic()
+        .context("failed to run editor");
+
+    // Editors often replace the file rather than writing to it, so read it again by path
+    let content = std::fs::read(&path)
+        .into_diagnostic()
+        .with_context(|| format!("failed to read temporary file: {}", path.display()));
+    if let Err(e) = file.close() {
+        tracing::warn!("failed to remove temporary file {}: {e}", path.display());
+    }
+
+    let status = status?;
+    if !status.success() {
+        return Err(miette!("editor exited unsuccessfully: {status}"));
+    }
+
+    let content = content?;
+    if content == snippet.content {
+        tracing::debug!("snippet unchanged");
+        return Ok(());
+    }
+    if content.is_empty() {
+        return Err(miette!(
+            help = "use `clipvault snippet rm` to remove the snippet",
+            "snippet can't be empty"
+        ));
+    }
+
+    let mime_type = snippet
+        .mime_type
+        .unwrap_or_else(|| detect_mime_type(&content));
+    upsert_snippet(&init_db(path_db)?, name, &content, &mime_type, true)
+}
+
+#[tracing::instrument(skip(path_db))]
+pub fn execute(path_db: &Path, command: SnippetCommand) -> Result<()> {
+    match command {
+        SnippetCommand::Add(args) => add(path_db, args),
+        SnippetCommand::Get(SnippetArgs { name }) => {
+            let snippet = get_snippet(&init_db(path_db)?, &name)?;
+            write_stdout(&snippet.content)
+        }
+        SnippetCommand::List => list(path_db),
+        SnippetCommand::Rm(SnippetArgs { name }) => delete_snippet(&init_db(path_db)?, &name),
+        SnippetCommand::Edit(SnippetArgs { name }) => edit(path_db, &name),
+    }
+}
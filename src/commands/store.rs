@@ -5,17 +5,154 @@ use std::{
 
 use content_inspector::ContentType;
 use miette::{Context, IntoDiagnostic, Result, miette};
+use regex::Regex;
+use rusqlite::Connection;
 use tracing::instrument;
 
 use crate::{
-    cli::StoreArgs,
+    cli::{Dimensions, IgnoreArgs, SecretAction, StoreArgs},
     database::{
+        data::{EntryMetadata, EntryPart, PruneOptions},
         init_db,
-        queries::{delete_all_entries, delete_entries_older_than, trim_entries, upsert_entry},
+        queries::{
+            add_entry_tag, delete_all_entries, delete_entries_older_than, delete_expired_entries,
+            get_pause, reclaim_space, replace_entry_parts, set_pinned_by_id, trim_entries,
+            upsert_entry,
+        },
+        write_transaction,
     },
-    utils::now,
+    rules::{RuleAction, RuleInput, first_match, glob_match, local_time},
+    secrets,
+    utils::{detect_mime_type, now},
 };
 
+/// Whether the content should not be stored, as it is outside the size bounds, only whitespace, or
+/// text matching one of the ignore patterns. A maximum of 0 disables the limit.
+pub(super) fn is_ignored(
+    content: &[u8],
+    min_bytes: usize,
+    max_bytes: usize,
+    ignore_pattern: Option<&[Regex]>,
+) -> bool {
+    // Ignore content larger than the max size or smaller than the min size in bytes
+    let gt_max = content.len() > max_bytes && max_bytes != 0;
+    let lt_min = content.len() < min_bytes;
+    if gt_max || lt_min {
+        tracing::debug!(
+            "content length ({}) is outside the bounds {min_bytes}->{max_bytes}",
+            content.len()
+        );
+        return true;
+    }
+
+    // Ignore purely whitespace content
+    if content.trim_ascii().is_empty() {
+        tracing::debug!("only ASCII whitespace content");
+        return true;
+    }
+
+    // Check user-provided ignore pattern
+    if let Some(regexes) = ignore_pattern
+        && matches!(
+            content_inspector::inspect(content),
+            ContentType::UTF_8 | ContentType::UTF_8_BOM
+        )
+        && regexes
+            .iter()
+            .any(|re| re.is_match(&String::from_utf8_lossy(content)))
+    {
+        tracing::debug!("content matched an ignore pattern");
+        return true;
+    }
+
+    false
+}
+
+/// Whether the content should not be stored due to its MIME type, raw bytes or image dimensions.
+/// Unlike the ignore patterns, these also apply to binary content.
+pub(super) fn is_ignored_by(content: &[u8], mime_type: &str, args: &IgnoreArgs) -> bool {
+    if args
+        .ignore_mime
+        .iter()
+        .any(|pattern| glob_match(pattern, mime_type))
+    {
+        tracing::debug!("MIME type matched an ignore pattern: {mime_type}");
+        return true;
+    }
+
+    if args
+        .ignore_bytes
+        .iter()
+        .any(|pattern| pattern.0.is_match(content))
+    {
+        tracing::debug!("content matched an ignore pattern");
+        return true;
+    }
+
+    // Only decode images if there are limits to check
+    if args.min_image_size.is_none() && args.max_image_size.is_none() {
+        return false;
+    }
+    if let Some((width, height)) = EntryMetadata::from_content(content).dimensions {
+        let lt_min = args
+            .min_image_size
+            .is_some_and(|min| width < min.width || height < min.height);
+        let gt_max = args
+            .max_image_size
+            .is_some_and(|max| width > max.width || height > max.height);
+        if lt_min || gt_max {
+            let bound =
+                |d: Option<Dimensions>| d.map_or_else(|| "none".to_owned(), |d| d.to_string());
+            tracing::debug!(
+                "image dimensions ({width}x{height}) are outside the bounds {}->{}",
+                bound(args.min_image_size),
+                bound(args.max_image_size)
+            );
+            return true;
+        }
+    }
+
+    false
+}
+
+/// Whether capturing entries is currently paused, in which case nothing should be changed.
+fn is_paused(conn: &Connection) -> Result<bool> {
+    let paused = get_pause(conn)?.is_some();
+    if paused {
+        tracing::debug!("capture is paused - not storing");
+    }
+    Ok(paused)
+}
+
+/// Delete expired entries and entries older than the maximum age, then the oldest entries over the
+/// maximum number of entries, and return how many were deleted. A maximum of 0 disables the limit.
+pub(super) fn prune(
+    conn: &Connection,
+    max_entries: usize,
+    max_age: humantime::Duration,
+    options: PruneOptions,
+) -> Result<usize> {
+    let mut deleted = delete_expired_entries(conn, now())?;
+
+    // Delete old entries
+    let max_age = max_age.as_secs();
+    if max_age != 0 {
+        let timestamp = now().saturating_sub(max_age);
+        deleted += delete_entries_older_than(conn, timestamp, options)?;
+    }
+
+    // Trim entries if over limit
+    if max_entries != 0 {
+        deleted += trim_entries(conn, max_entries, options)?;
+    }
+
+    if deleted > 0 {
+        reclaim_space(conn)?;
+    }
+
+    Ok(deleted)
+}
+
 #[instrument]
 pub fn execute(path_db: &Path, args: StoreArgs) -> Result<()> {
     execute_with_source(path_db, args, stdin())
@@ -31,6 +168,19 @@ pub fn execute_with_source(path_db: &Path, args: StoreArgs, mut source: impl Rea
         min_entry_length: min_bytes,
         store_sensitive,
         ignore_pattern,
+        ignore,
+        mime_type,
+        part,
+        prune_pinned,
+        tag,
+        keep_tagged,
+        ttl,
+        on_secret,
+        secret_ttl,
+        secret_pattern,
+        secret_entropy,
+        source: source_app,
+        rules,
     } = args;
 
     // Min conflicts with max
@@ -41,7 +191,8 @@ pub fn execute_with_source(path_db: &Path, args: StoreArgs, mut source: impl Rea
     }
 
     // Set by `wl-clipboard`
-    if let Ok(s) = std::env::var("CLIPBOARD_STATE") {
+    let clipboard_state = std::env::var("CLIPBOARD_STATE").ok();
+    if let Some(s) = &clipboard_state {
         tracing::debug!("CLIPBOARD_STATE={s}");
         match s.as_str() {
             // Clipboard contains a sensitive value - skip if not storing sensitive values
@@ -55,7 +206,16 @@ pub fn execute_with_source(path_db: &Path, args: StoreArgs, mut source: impl Rea
             // As of writing, "clear" is not yet used by `wl-clipboard`.
             "clear" => {
                 tracing::debug!("explicitly cleared clipboard");
-                return delete_all_entries(&init_db(path_db)?);
+                let mut conn = init_db(path_db)?;
+                let tx = write_transaction(&mut conn)?;
+                if is_paused(&tx)? {
+                    return Ok(());
+                }
+                delete_all_entries(&tx, false)?;
+                return tx
+                    .commit()
+                    .into_diagnostic()
+                    .context("failed to commit transaction");
             }
             // Clipboard is empty - nothing to store
             "nil" => return Ok(()),
@@ -64,7 +224,7 @@ pub fn execute_with_source(path_db: &Path, args: StoreArgs, mut source: impl Rea
     };
 
     // Read input using given source - this should be STDIN for production code
-    let buf = {
+    let mut buf = {
         let mut buf = vec![];
         source
             .read_to_end(&mut buf)
@@ -79,54 +239,124 @@ pub fn execute_with_source(path_db: &Path, args: StoreArgs, mut source: impl Rea
         return Ok(());
     }
 
-    // Ignore content larger than the max size or smaller than the min size in bytes
-    let gt_max = buf.len() > max_bytes && max_bytes != 0;
-    let lt_min = buf.len() < min_bytes;
-    if gt_max || lt_min {
-        tracing::debug!(
-            "content length ({}) is outside the bounds {min_bytes}->{max_bytes}",
-            buf.len()
-        );
+    if is_ignored(&buf, min_bytes, max_bytes, ignore_pattern.as_deref()) {
         return Ok(());
     }
 
-    // Ignore purely whitespace content
-    if buf.trim_ascii().is_empty() {
-        tracing::debug!("only ASCII whitespace content");
+    let mime_type = mime_type.unwrap_or_else(|| detect_mime_type(&buf));
+    tracing::debug!("MIME type: {mime_type}");
+    if is_ignored_by(&buf, &mime_type, &ignore) {
         return Ok(());
     }
 
-    // Check user-provided ignore pattern
-    if let Some(regexes) = ignore_pattern
-        && matches!(
-            content_inspector::inspect(&buf),
-            ContentType::UTF_8 | ContentType::UTF_8_BOM
-        )
-        && regexes
-            .iter()
-            .any(|re| re.is_match(&String::from_utf8_lossy(&buf)))
-    {
-        tracing::debug!("content matched an ignore pattern");
-        return Ok(());
+    // Only the first matching rule from the config file applies
+    let input = RuleInput::new(
+        &buf,
+        &mime_type,
+        clipboard_state.as_deref(),
+        source_app.as_deref(),
+        local_time(),
+    );
+    let rule = first_match(&rules, &input);
+    if let Some((i, rule)) = rule {
+        tracing::debug!("matched rule: {}", rule.name(i));
+        if rule.action == RuleAction::Ignore {
+            return Ok(());
+        }
+
+        // The transformed content must still pass the same checks as the original
+        buf = rule.transform(buf);
+        if is_ignored(&buf, min_bytes, max_bytes, ignore_pattern.as_deref())
+            || is_ignored_by(&buf, &mime_type, &ignore)
+        {
+            return Ok(());
+        }
+    }
+
+    // Content which looks like a secret is either rejected, or only kept for a short time
+    let secret = (on_secret != SecretAction::Keep)
+        .then(|| secrets::detect(&buf, &secret_pattern, secret_entropy))
+        .flatten();
+    let secret_expiry = match (secret, on_secret) {
+        (None, _) => None,
+        (Some(rule), SecretAction::Reject) => {
+            tracing::debug!("content matched secret rule: {rule} - not storing");
+            return Ok(());
+        }
+        (Some(rule), _) => {
+            tracing::debug!("content matched secret rule: {rule} - expires in {secret_ttl}");
+            Some(now() + secret_ttl.as_secs())
+        }
+    };
+    let rule_ttl = rule.and_then(|(_, rule)| rule.ttl);
+    let expires_at = [ttl, rule_ttl]
+        .into_iter()
+        .flatten()
+        .map(|ttl| now() + ttl.as_secs())
+        .chain(secret_expiry)
+        .min();
+
+    // Read additional representations of the entry
+    let mut parts = Vec::with_capacity(part.len());
+    for (part_mime_type, path) in part {
+        if part_mime_type == mime_type {
+            tracing::debug!("part has the same MIME type as the main content: {part_mime_type}");
+            continue;
+        }
+
+        let content = std::fs::read(&path)
+            .into_diagnostic()
+            .with_context(|| format!("failed to read part: {}", path.display()))?;
+
+        if content.is_empty() || (content.len() > max_bytes && max_bytes != 0) {
+            tracing::debug!(
+                "part length ({}) is outside the bounds 1->{max_bytes}: {part_mime_type}",
+                content.len()
+            );
+            continue;
+        }
+
+        parts.push(EntryPart {
+            mime_type: part_mime_type,
+            content,
+        });
     }
 
     // Only get DB connection after parsing STDIN - avoid locking
-    let conn = &init_db(path_db)?;
+    let mut conn = init_db(path_db)?;
 
-    // Delete old entries
-    let max_age = max_age.as_secs();
-    if max_age != 0 {
-        let timestamp = now() - max_age;
-        delete_entries_older_than(conn, timestamp)?;
+    let prune_options = PruneOptions {
+        include_pinned: prune_pinned,
+        keep_tagged,
+    };
+
+    // Take the write lock up front, so that other processes never see a partially stored entry or
+    // half-pruned history, and concurrent stores wait for the busy timeout instead of failing
+    let tx = write_transaction(&mut conn)?;
+
+    // Checked while holding the lock, so that a pause can't start before the entry is stored
+    if is_paused(&tx)? {
+        return Ok(());
     }
 
     // Upsert new entry
-    upsert_entry(conn, &buf)?;
-
-    // Trim entries if over limit
-    if max_entries != 0 {
-        trim_entries(conn, max_entries)?;
+    let id = upsert_entry(&tx, &buf, &mime_type, expires_at)?;
+    if !parts.is_empty() {
+        replace_entry_parts(&tx, id, &parts)?;
     }
+    let rule_tags = rule
+        .map(|(_, rule)| rule.tags.as_slice())
+        .unwrap_or_default();
+    for t in tag.iter().chain(rule_tags) {
+        add_entry_tag(&tx, id, t)?;
+    }
+    if rule.is_some_and(|(_, rule)| rule.pin) {
+        set_pinned_by_id(&tx, id, true)?;
+    }
+
+    prune(&tx, max_entries, max_age, prune_options)?;
 
-    Ok(())
+    tx.commit()
+        .into_diagnostic()
+        .context("failed to commit transaction")
 }
@@ -0,0 +1,98 @@
+use std::{
+    io::{Read, Write, stdin, stdout},
+    path::Path,
+};
+
+use miette::{Context, IntoDiagnostic, Result, miette};
+use rusqlite::Connection;
+
+use super::{SEPARATOR, extract_id, wrap_index};
+use crate::{
+    cli::{GetDelArgs, TagCommand, TagEntryArgs, TagListArgs},
+    database::{
+        init_db,
+        queries::{
+            add_entry_tag, count_entries, get_entry_id_by_position, get_entry_tags, get_tags,
+            remove_entry_tag,
+        },
+    },
+    utils::ignore_broken_pipe,
+};
+
+/// Open the database and get the ID of the selected entry, from either the input or the relative
+/// index.
+fn entry_id(path_db: &Path, mut input: String, index: Option<isize>) -> Result<(Connection, u64)> {
+    assert!(
+        index.is_none() || index.is_some_and(|_| input.is_empty()),
+        "conflicting relative index and input - only one of these should make it to this stage"
+    );
+
+    if let Some(i) = index {
+        let conn = init_db(path_db)?;
+        let len = count_entries(&conn)?;
+        if len == 0 {
+            return Err(miette!("there are currently no saved clipboard entries"));
+        }
+
+        let id = get_entry_id_by_position(&conn, wrap_index(len, i))?;
+        return Ok((conn, id));
+    }
+
+    // Read from STDIN if no argument given - before opening the database to avoid locking
+    if input.is_empty() {
+        stdin()
+            .lock()
+            .read_to_string(&mut input)
+            .into_diagnostic()
+            .context("failed to read STDIN")?;
+    }
+
+    let id = extract_id(input)?;
+    Ok((init_db(path_db)?, id))
+}
+
+fn list(path_db: &Path, args: TagListArgs) -> Result<()> {
+    let TagListArgs { input, index } = args;
+
+    let output = if input.is_none() && index.is_none() {
+        get_tags(&init_db(path_db)?)?
+            .into_iter()
+            .map(|(tag, count)| format!("{tag}{SEPARATOR}{count}\n"))
+            .collect::<String>()
+    } else {
+        let (conn, id) = entry_id(path_db, input.unwrap_or_default(), index)?;
+        get_entry_tags(&conn, id)?
+            .into_iter()
+            .map(|tag| tag + "\n")
+            .collect()
+    };
+
+    let mut stdout = stdout().lock();
+    ignore_broken_pipe(stdout.write_all(output.as_bytes()))
+        .into_diagnostic()
+        .context("failed to write to STDOUT")?;
+    ignore_broken_pipe(stdout.flush())
+        .into_diagnostic()
+        .context("failed to flush STDOUT")
+}
+
+#[tracing::instrument(skip(path_db))]
+pub fn execute(path_db: &Path, command: TagCommand) -> Result<()> {
+    match command {
+        TagCommand::Add(TagEntryArgs {
+            tag,
+            entry: GetDelArgs { input, index },
+        }) => {
+            let (conn, id) = entry_id(path_db, input, index)?;
+            add_entry_tag(&conn, id, &tag)
+        }
+        TagCommand::Remove(TagEntryArgs {
+            tag,
+            entry: GetDelArgs { input, index },
+        }) => {
+            let (conn, id) = entry_id(path_db, input, index)?;
+            remove_entry_tag(&conn, id, &tag)
+        }
+        TagCommand::List(args) => list(path_db, args),
+    }
+}
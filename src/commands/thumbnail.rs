@@ -0,0 +1,152 @@
+use std::{
+    collections::HashSet,
+    fmt::Write as _,
+    fs::{create_dir_all, read_dir, remove_file},
+    io::{Write, stdout},
+    path::{Path, PathBuf},
+};
+
+use image::ImageFormat;
+use miette::{Context, IntoDiagnostic, Result, miette};
+use rusqlite::Connection;
+
+use super::SEPARATOR;
+use crate::{
+    cli::ThumbnailArgs,
+    database::{
+        init_db,
+        queries::{get_entry_by_id, get_image_entries, is_encrypted},
+    },
+    utils::ignore_broken_pipe,
+};
+
+const EXTENSION: &str = "png";
+
+/// Number of bytes of the content hash included in the names of thumbnails.
+const HASH_PREFIX_LEN: usize = 8;
+
+/// Name of the thumbnail for an entry, without the extension. Including the content hash means
+/// that a re-used ID or changed content never shows an outdated thumbnail.
+fn file_stem(id: u64, hash: &[u8]) -> String {
+    hash.iter()
+        .take(HASH_PREFIX_LEN)
+        .fold(format!("{id}-"), |mut stem, b| {
+            let _ = write!(stem, "{b:02x}");
+            stem
+        })
+}
+
+/// Whether the file name is exactly in the format written by [`file_stem`]. The directory may be
+/// shared with other files, which must never be deleted.
+fn is_thumbnail_stem(stem: &str) -> bool {
+    stem.split_once('-').is_some_and(|(id, hash)| {
+        id.parse::<u64>().is_ok()
+            && hash.len() == HASH_PREFIX_LEN * 2
+            && hash
+                .bytes()
+                .all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b))
+    })
+}
+
+/// Write a thumbnail of the image entry with the given ID to the given path.
+fn generate(conn: &Connection, id: u64, path: &Path, size: u32) -> Result<()> {
+    let entry = get_entry_by_id(conn, id)?;
+
+    image::load_from_memory(&entry.content)
+        .into_diagnostic()
+        .context("failed to decode image")?
+        .thumbnail(size, size)
+        .save_with_format(path, ImageFormat::Png)
+        .into_diagnostic()
+        .context("failed to write thumbnail")
+}
+
+/// Ensure that the given directory contains an up-to-date thumbnail for every image entry, and
+/// no thumbnails for entries which no longer exist. Returns the entry IDs and thumbnail paths,
+/// newest first.
+///
+/// Thumbnails would be unencrypted copies of the images, so are never generated for encrypted
+/// databases.
+#[tracing::instrument(skip(conn))]
+pub(super) fn sync_thumbnails(
+    conn: &Connection,
+    args: &ThumbnailArgs,
+) -> Result<Vec<(u64, PathBuf)>> {
+    let ThumbnailArgs {
+        thumbnail_dir: dir,
+        thumbnail_size: size,
+    } = args;
+
+    if is_encrypted(conn)? {
+        return Err(miette!(
+            help = "thumbnails would be written as unencrypted images",
+            "thumbnails are not available for encrypted databases"
+        ));
+    }
+
+    if !dir.exists() {
+        create_dir_all(dir)
+            .into_diagnostic()
+            .context("failed to create thumbnails directory")?;
+    }
+
+    let mut thumbnails = Vec::new();
+    for (id, hash) in get_image_entries(conn)? {
+        let path = dir.join(format!("{}.{EXTENSION}", file_stem(id, &hash)));
+
+        if !path.exists() {
+            tracing::debug!("generating thumbnail: {}", path.display());
+            if let Err(e) = generate(conn, id, &path, *size) {
+                tracing::warn!("failed to generate thumbnail for entry {id}: {e:?}");
+                continue;
+            }
+        }
+
+        thumbnails.push((id, path));
+    }
+    let current = thumbnails
+        .iter()
+        .map(|(_, path)| path.clone())
+        .collect::<HashSet<_>>();
+
+    // Delete thumbnails for entries which no longer exist, or whose content has changed
+    let files = read_dir(dir)
+        .into_diagnostic()
+        .context("failed to read thumbnails directory")?;
+    for path in files.filter_map(|f| f.ok().map(|f| f.path())) {
+        let is_thumbnail = path.extension().is_some_and(|ext| ext == EXTENSION)
+            && path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .is_some_and(is_thumbnail_stem);
+
+        if is_thumbnail && !current.contains(&path) {
+            tracing::debug!("deleting thumbnail: {}", path.display());
+            remove_file(&path)
+                .into_diagnostic()
+                .with_context(|| format!("failed to delete thumbnail: {}", path.display()))?;
+        }
+    }
+
+    Ok(thumbnails)
+}
+
+#[tracing::instrument(skip(path_db))]
+pub fn execute(path_db: &Path, args: ThumbnailArgs) -> Result<()> {
+    let thumbnails = sync_thumbnails(&init_db(path_db)?, &args)?;
+
+    let output = thumbnails
+        .iter()
+        .map(|(id, path)| format!("{id}{SEPARATOR}{}\n", path.display()))
+        .collect::<String>();
+
+    let mut stdout = stdout().lock();
+    ignore_broken_pipe(stdout.write_all(output.as_bytes()))
+        .into_diagnostic()
+        .context("failed to write to STDOUT")?;
+    ignore_broken_pipe(stdout.flush())
+        .into_diagnostic()
+        .context("failed to flush STDOUT")?;
+
+    Ok(())
+}
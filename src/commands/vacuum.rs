@@ -0,0 +1,32 @@
+use std::path::Path;
+
+use miette::Result;
+
+use crate::{
+    database::{
+        init_db_without_encryption,
+        queries::{checkpoint, get_db_size, optimize_search_index, vacuum},
+    },
+    utils::human_bytes,
+};
+
+#[tracing::instrument(skip(path_db))]
+pub fn execute(path_db: &Path) -> Result<()> {
+    // Content is never read, so the key isn't needed
+    let conn = init_db_without_encryption(path_db)?;
+    let before = get_db_size(&conn)?;
+
+    optimize_search_index(&conn)?;
+    vacuum(&conn)?;
+    checkpoint(&conn)?;
+
+    let after = get_db_size(&conn)?;
+    println!(
+        "{} -> {} (reclaimed {})",
+        human_bytes(before),
+        human_bytes(after),
+        human_bytes(before.saturating_sub(after))
+    );
+
+    Ok(())
+}
@@ -0,0 +1,482 @@
+use std::{
+    fmt::Display,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use clap::{ArgMatches, parser::ValueSource};
+use miette::{Context, IntoDiagnostic, LabeledSpan, NamedSource, Result, miette};
+use regex::Regex;
+use serde::{Deserialize, Deserializer};
+
+use crate::{
+    cli::{
+        BytePattern, Cli, Commands, Dimensions, IgnoreArgs, ImportArgs, ListArgs, ListFormat,
+        MergeArgs, RulesCommand, SecretAction, StoreArgs, ThumbnailArgs,
+    },
+    rules::Rule,
+    secrets::SecretRule,
+    template::Template,
+};
+
+/// Settings loaded from the TOML config file.
+///
+/// Every value is optional, and is only used if the equivalent CLI argument or environment
+/// variable was not given. Keys use the same names as the CLI arguments, e.g. `max-entries`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    pub database: Option<PathBuf>,
+    pub key_file: Option<PathBuf>,
+    #[serde(deserialize_with = "from_str")]
+    pub busy_timeout: Option<humantime::Duration>,
+    pub store: StoreConfig,
+    pub list: ListConfig,
+    pub thumbnail: ThumbnailConfig,
+    /// Rules for storing entries, read from the `[[rule]]` tables and checked in order.
+    #[serde(rename = "rule")]
+    pub rules: Vec<Rule>,
+}
+
+/// Settings for the `store` command, read from the `[store]` section.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct StoreConfig {
+    pub max_entries: Option<usize>,
+    #[serde(deserialize_with = "from_str")]
+    pub max_entry_age: Option<humantime::Duration>,
+    pub max_entry_length: Option<usize>,
+    pub min_entry_length: Option<usize>,
+    pub store_sensitive: Option<bool>,
+    #[serde(deserialize_with = "from_str_seq")]
+    pub ignore_pattern: Option<Vec<Regex>>,
+    pub ignore_mime: Option<Vec<String>>,
+    #[serde(deserialize_with = "from_str_seq")]
+    pub ignore_bytes: Option<Vec<BytePattern>>,
+    #[serde(deserialize_with = "from_str")]
+    pub min_image_size: Option<Dimensions>,
+    #[serde(deserialize_with = "from_str")]
+    pub max_image_size: Option<Dimensions>,
+    pub prune_pinned: Option<bool>,
+    pub keep_tagged: Option<bool>,
+    pub on_secret: Option<SecretAction>,
+    #[serde(deserialize_with = "from_str")]
+    pub secret_ttl: Option<humantime::Duration>,
+    #[serde(deserialize_with = "from_str_seq")]
+    pub secret_pattern: Option<Vec<SecretRule>>,
+    pub secret_entropy: Option<bool>,
+}
+
+/// Settings for the `list` command, read from the `[list]` section.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ListConfig {
+    pub max_preview_width: Option<usize>,
+    pub reverse: Option<bool>,
+    pub pinned_first: Option<bool>,
+    pub snippets: Option<bool>,
+    pub format: Option<ListFormat>,
+    #[serde(deserialize_with = "from_str")]
+    pub template: Option<Template>,
+}
+
+/// Settings for thumbnails, read from the `[thumbnail]` section. Used by both the `thumbnail`
+/// and `list` commands.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ThumbnailConfig {
+    pub thumbnail_dir: Option<PathBuf>,
+    pub thumbnail_size: Option<u32>,
+}
+
+/// Deserialize a value using its [`FromStr`] implementation.
+pub(crate) fn from_str<'de, D, T>(deserializer: D) -> std::result::Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: Display,
+{
+    String::deserialize(deserializer)?
+        .parse()
+        .map(Some)
+        .map_err(serde::de::Error::custom)
+}
+
+/// Deserialize a sequence of values using their [`FromStr`] implementation.
+fn from_str_seq<'de, D, T>(deserializer: D) -> std::result::Result<Option<Vec<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: Display,
+{
+    Vec::<String>::deserialize(deserializer)?
+        .iter()
+        .map(|s| s.parse().map_err(serde::de::Error::custom))
+        .collect::<std::result::Result<_, _>>()
+        .map(Some)
+}
+
+/// Whether the value for the argument with the given ID should be taken from the config file,
+/// i.e. it was not given through the CLI or an environment variable.
+fn use_config(matches: &ArgMatches, id: &str) -> bool {
+    matches!(
+        matches.value_source(id),
+        None | Some(ValueSource::DefaultValue)
+    )
+}
+
+/// Overwrite each of the given fields of the args with the value from the config, if one is set
+/// and the argument was not given explicitly.
+macro_rules! merge {
+    ($args:expr, $config:expr, $matches:expr, [$($field:ident),+ $(,)?]) => {
+        $(
+            if let Some(value) = $config.$field
+                && use_config($matches, stringify!($field))
+            {
+                tracing::trace!("using value from config: {}", stringify!($field));
+                $args.$field = value;
+            }
+        )+
+    };
+}
+
+impl Config {
+    /// Read and parse the config file at the given path.
+    ///
+    /// If `required` is not set, a missing file is treated the same as an empty one.
+    #[tracing::instrument]
+    pub fn load(path: &Path, required: bool) -> Result<Self> {
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == ErrorKind::NotFound && !required => {
+                tracing::debug!("no config file found");
+                return Ok(Self::default());
+            }
+            Err(e) => {
+                return Err(e)
+                    .into_diagnostic()
+                    .with_context(|| format!("failed to read config file: {}", path.display()));
+            }
+        };
+
+        Self::parse(&contents, &path.display().to_string())
+    }
+
+    /// Parse the contents of a config file. The name is only used for error messages.
+    pub fn parse(contents: &str, name: &str) -> Result<Self> {
+        toml::from_str(contents).map_err(|e| {
+            let labels = e
+                .span()
+                .map(|span| LabeledSpan::at(span, e.message().to_owned()))
+                .into_iter()
+                .collect::<Vec<_>>();
+
+            miette!(labels = labels, "failed to parse config file")
+                .with_source_code(NamedSource::new(name, contents.to_owned()))
+        })
+    }
+
+    /// Fill in any values for the given CLI args which were not explicitly provided.
+    pub fn apply(self, cli: &mut Cli, matches: &ArgMatches) {
+        if let Some(database) = self.database
+            && use_config(matches, "database")
+        {
+            cli.database = database;
+        }
+
+        if let Some(busy_timeout) = self.busy_timeout
+            && use_config(matches, "busy_timeout")
+        {
+            cli.busy_timeout = busy_timeout;
+        }
+
+        if let Some(key_file) = self.key_file
+            && cli.key.passphrase.is_none()
+            && use_config(matches, "key_file")
+        {
+            cli.key.key_file = Some(key_file);
+        }
+
+        match (&mut cli.command, matches.subcommand()) {
+            (Commands::Store(args), Some((_, matches))) => {
+                self.store.apply(args, matches);
+                args.rules = self.rules;
+            }
+            (Commands::Rules(RulesCommand::Test(args)), _) => args.rules = self.rules,
+            (Commands::Import(args), Some((_, matches))) => {
+                self.store.apply_import(args, matches);
+            }
+            (Commands::Merge(args), Some((_, matches))) => self.store.apply_merge(args, matches),
+            (Commands::List(args), Some((_, matches))) => {
+                self.thumbnail.apply(&mut args.thumbnails, matches);
+                self.list.apply(args, matches);
+            }
+            (Commands::Search(args), Some((_, matches))) => {
+                self.thumbnail.apply(&mut args.list.thumbnails, matches);
+                self.list.apply(&mut args.list, matches);
+            }
+            (Commands::Thumbnail(args), Some((_, matches))) => self.thumbnail.apply(args, matches),
+            _ => {}
+        }
+    }
+}
+
+impl StoreConfig {
+    fn apply(mut self, args: &mut StoreArgs, matches: &ArgMatches) {
+        self.apply_ignore(&mut args.ignore, matches);
+        merge!(
+            args,
+            self,
+            matches,
+            [
+                max_entries,
+                max_entry_age,
+                max_entry_length,
+                min_entry_length,
+                store_sensitive,
+                prune_pinned,
+                keep_tagged,
+                on_secret,
+                secret_ttl,
+                secret_pattern,
+                secret_entropy,
+            ]
+        );
+
+        if let Some(patterns) = self.ignore_pattern
+            && use_config(matches, "ignore_pattern")
+        {
+            args.ignore_pattern = Some(patterns);
+        }
+    }
+
+    /// Imported entries are filtered in the same way as stored entries.
+    fn apply_import(mut self, args: &mut ImportArgs, matches: &ArgMatches) {
+        self.apply_ignore(&mut args.ignore, matches);
+        merge!(args, self, matches, [max_entry_length, min_entry_length]);
+
+        if let Some(patterns) = self.ignore_pattern
+            && use_config(matches, "ignore_pattern")
+        {
+            args.ignore_pattern = Some(patterns);
+        }
+    }
+
+    fn apply_ignore(&mut self, args: &mut IgnoreArgs, matches: &ArgMatches) {
+        if let Some(patterns) = self.ignore_mime.take()
+            && use_config(matches, "ignore_mime")
+        {
+            args.ignore_mime = patterns;
+        }
+        if let Some(patterns) = self.ignore_bytes.take()
+            && use_config(matches, "ignore_bytes")
+        {
+            args.ignore_bytes = patterns;
+        }
+        if let Some(size) = self.min_image_size
+            && use_config(matches, "min_image_size")
+        {
+            args.min_image_size = Some(size);
+        }
+        if let Some(size) = self.max_image_size
+            && use_config(matches, "max_image_size")
+        {
+            args.max_image_size = Some(size);
+        }
+    }
+
+    /// The history is pruned after merging in the same way as after storing an entry.
+    fn apply_merge(self, args: &mut MergeArgs, matches: &ArgMatches) {
+        merge!(
+            args,
+            self,
+            matches,
+            [max_entries, max_entry_age, prune_pinned, keep_tagged]
+        );
+    }
+}
+
+impl ListConfig {
+    fn apply(self, args: &mut ListArgs, matches: &ArgMatches) {
+        merge!(
+            args,
+            self,
+            matches,
+            [max_preview_width, reverse, pinned_first, snippets, format]
+        );
+
+        if let Some(template) = self.template
+            && use_config(matches, "template")
+        {
+            args.template = Some(template);
+        }
+    }
+}
+
+impl ThumbnailConfig {
+    fn apply(self, args: &mut ThumbnailArgs, matches: &ArgMatches) {
+        merge!(args, self, matches, [thumbnail_dir, thumbnail_size]);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use clap::{CommandFactory, FromArgMatches};
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::rules::RuleAction;
+
+    fn parse_with(config: &str, args: &[&str]) -> Cli {
+        let matches = Cli::command().get_matches_from(args);
+        let mut cli = Cli::from_arg_matches(&matches).unwrap();
+        Config::parse(config, "config.toml")
+            .unwrap()
+            .apply(&mut cli, &matches);
+        cli
+    }
+
+    #[test]
+    fn test_parse_empty() {
+        let config = Config::parse("", "config.toml").unwrap();
+        assert!(config.database.is_none());
+        assert!(config.store.max_entries.is_none());
+        assert!(config.list.reverse.is_none());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        // Unknown keys
+        assert!(Config::parse("unknown = 1", "config.toml").is_err());
+        assert!(Config::parse("[store]\nmax-entriess = 1", "config.toml").is_err());
+        assert!(Config::parse("[unknown]", "config.toml").is_err());
+
+        // Invalid values
+        assert!(Config::parse("[store]\nmax-entries = -1", "config.toml").is_err());
+        assert!(Config::parse("[store]\nmax-entry-age = \"1 fortnight\"", "config.toml").is_err());
+        assert!(Config::parse("[store]\nignore-pattern = [\"[[\"]", "config.toml").is_err());
+        assert!(Config::parse("[store]\nsecret-pattern = [\"[[\"]", "config.toml").is_err());
+        assert!(Config::parse("[store]\non-secret = \"delete\"", "config.toml").is_err());
+        assert!(Config::parse("[store]\nignore-bytes = [\"[[\"]", "config.toml").is_err());
+        assert!(Config::parse("[store]\nmin-image-size = \"16\"", "config.toml").is_err());
+        assert!(Config::parse("[list]\nreverse = \"yes\"", "config.toml").is_err());
+    }
+
+    #[test]
+    fn test_rules() {
+        let config = r#"
+            [[rule]]
+            name = "codes"
+            pattern = '^\d{6}$'
+
+            [[rule]]
+            action = "ignore"
+        "#;
+
+        for command in ["store", "rules test"] {
+            let mut args = vec!["clipvault"];
+            args.extend(command.split(' '));
+            let rules = match parse_with(config, &args).command {
+                Commands::Store(args) => args.rules,
+                Commands::Rules(RulesCommand::Test(args)) => args.rules,
+                _ => panic!("unexpected command"),
+            };
+            assert_eq!(rules.len(), 2, "{command}");
+            assert_eq!(rules[0].name.as_deref(), Some("codes"));
+            assert_eq!(rules[1].action, RuleAction::Ignore);
+        }
+
+        assert!(Config::parse("[[rule]]\nactoin = \"ignore\"", "config.toml").is_err());
+        assert!(Config::parse("[rule]\naction = \"ignore\"", "config.toml").is_err());
+    }
+
+    #[test]
+    fn test_precedence() {
+        let config = r#"
+            database = "/tmp/config.db"
+            busy-timeout = "10s"
+
+            [store]
+            max-entries = 5
+            max-entry-age = "1h"
+            ignore-pattern = ["^abc"]
+            ignore-mime = ["image/gif"]
+            min-image-size = "16x16"
+
+            [list]
+            reverse = true
+        "#;
+
+        // Config over defaults
+        let cli = parse_with(config, &["clipvault", "store"]);
+        assert_eq!(cli.database, PathBuf::from("/tmp/config.db"));
+        assert_eq!(cli.busy_timeout.as_secs(), 10);
+        let Commands::Store(args) = cli.command else {
+            panic!("expected store command");
+        };
+        assert_eq!(args.max_entries, 5);
+        assert_eq!(args.max_entry_age.as_secs(), 3600);
+        assert_eq!(args.ignore_pattern.map(|p| p.len()), Some(1));
+        assert_eq!(args.min_entry_length, crate::defaults::MIN_ENTRY_LEN);
+        assert_eq!(args.ignore.ignore_mime, ["image/gif"]);
+        assert_eq!(
+            args.ignore.min_image_size,
+            Some(Dimensions {
+                width: 16,
+                height: 16
+            })
+        );
+
+        // CLI over config
+        let cli = parse_with(
+            config,
+            &[
+                "clipvault",
+                "store",
+                "--max-entries",
+                "7",
+                "--database",
+                "/tmp/cli.db",
+                "--busy-timeout",
+                "1s",
+            ],
+        );
+        assert_eq!(cli.database, PathBuf::from("/tmp/cli.db"));
+        assert_eq!(cli.busy_timeout.as_secs(), 1);
+        let Commands::Store(args) = cli.command else {
+            panic!("expected store command");
+        };
+        assert_eq!(args.max_entries, 7);
+        assert_eq!(args.max_entry_age.as_secs(), 3600);
+
+        // Store filters also apply to imported entries
+        let cli = parse_with(config, &["clipvault", "import"]);
+        let Commands::Import(args) = cli.command else {
+            panic!("expected import command");
+        };
+        assert_eq!(args.ignore_pattern.map(|p| p.len()), Some(1));
+        assert_eq!(args.ignore.ignore_mime, ["image/gif"]);
+
+        // As does pruning after merging
+        let cli = parse_with(config, &["clipvault", "merge", "--from", "/tmp/other.db"]);
+        let Commands::Merge(args) = cli.command else {
+            panic!("expected merge command");
+        };
+        assert_eq!(args.max_entries, 5);
+        assert_eq!(args.max_entry_age.as_secs(), 3600);
+
+        let cli = parse_with(config, &["clipvault", "list"]);
+        let Commands::List(args) = cli.command else {
+            panic!("expected list command");
+        };
+        assert!(args.reverse);
+
+        // Search results are listed the same way
+        let cli = parse_with(config, &["clipvault", "search", "query"]);
+        let Commands::Search(args) = cli.command else {
+            panic!("expected search command");
+        };
+        assert!(args.list.reverse);
+    }
+}
@@ -0,0 +1,97 @@
+//! Transparent compression of the content of large text entries.
+//!
+//! Content is compressed before it is encrypted, and the codec is stored alongside it. Queries
+//! read the content through the `decompress` SQL function, so that the rest of the app only ever
+//! sees the original content. Duplicates are detected using the hash of the original content.
+
+use std::borrow::Cow;
+
+use miette::{Context, IntoDiagnostic, Result};
+use rusqlite::{
+    Connection,
+    functions::FunctionFlags,
+    types::{Value, ValueRef},
+};
+
+use super::data::EntryKind;
+
+/// Text entries larger than this many bytes are compressed.
+const COMPRESSION_THRESHOLD: usize = 4096;
+
+/// Default level of zstd, which is fast while still greatly reducing the size of text.
+const ZSTD_LEVEL: i32 = 3;
+
+/// Name of the codec stored for content compressed with zstd.
+const CODEC_ZSTD: &str = "zstd";
+
+/// Compress the content if it is a large text entry, returning the content to store and the
+/// codec used, if any.
+pub(super) fn compress(content: &[u8], kind: EntryKind) -> Result<(Cow<'_, [u8]>, Option<&str>)> {
+    if kind != EntryKind::Text || content.len() <= COMPRESSION_THRESHOLD {
+        return Ok((Cow::Borrowed(content), None));
+    }
+
+    let compressed = zstd::encode_all(content, ZSTD_LEVEL)
+        .into_diagnostic()
+        .context("failed to compress content")?;
+    tracing::debug!("compressed {} bytes to {}", content.len(), compressed.len());
+
+    Ok((Cow::Owned(compressed), Some(CODEC_ZSTD)))
+}
+
+/// Decompress content stored with the given codec.
+fn decompress(data: &[u8], codec: Option<&str>) -> std::io::Result<Vec<u8>> {
+    match codec {
+        None => Ok(data.to_vec()),
+        Some(CODEC_ZSTD) => zstd::decode_all(data),
+        Some(codec) => Err(std::io::Error::other(format!("unknown codec: {codec}"))),
+    }
+}
+
+/// Register the `decompress(content, codec)` SQL function.
+pub(super) fn setup(conn: &Connection) -> Result<()> {
+    let flags = FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC;
+
+    conn.create_scalar_function("decompress", 2, flags, |ctx| {
+        let codec = ctx.get::<Option<String>>(1)?;
+        if codec.is_none() {
+            return ctx.get::<Value>(0);
+        }
+
+        match ctx.get_raw(0) {
+            ValueRef::Blob(b) => decompress(b, codec.as_deref())
+                .map(Value::Blob)
+                .map_err(|e| rusqlite::Error::UserFunctionError(e.into())),
+            value => Ok(value.into()),
+        }
+    })
+    .into_diagnostic()
+    .context("failed to register function: decompress")
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_compress() {
+        let small = b"small text";
+        assert_eq!(
+            compress(small, EntryKind::Text).unwrap(),
+            (Cow::Borrowed(&small[..]), None)
+        );
+
+        let large = "some log line\n".repeat(1000);
+        assert_eq!(
+            compress(large.as_bytes(), EntryKind::Binary).unwrap().1,
+            None
+        );
+
+        let (compressed, codec) = compress(large.as_bytes(), EntryKind::Text).unwrap();
+        assert_eq!(codec, Some(CODEC_ZSTD));
+        assert!(compressed.len() < large.len());
+        assert_eq!(decompress(&compressed, codec).unwrap(), large.as_bytes());
+    }
+}
@@ -1,10 +1,43 @@
-use rusqlite::Row;
+use std::io::Cursor;
+
+use content_inspector::ContentType;
+use image::ImageReader;
+use rusqlite::{
+    Row, ToSql,
+    types::{FromSql, FromSqlError, FromSqlResult, ToSqlOutput, ValueRef},
+};
+use serde::Deserialize;
+
+use crate::{defaults, utils::truncate};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ClipboardEntry {
     pub id: u64,
     pub content: Vec<u8>,
     pub last_updated: u64,
+    /// `None` for entries stored before MIME types were recorded.
+    pub mime_type: Option<String>,
+}
+
+/// An additional representation of a clipboard entry, e.g. `text/html` alongside `text/plain`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntryPart {
+    pub mime_type: String,
+    pub content: Vec<u8>,
+}
+
+/// An entry along with everything needed to recreate it in another database, as used for
+/// exporting entries.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FullEntry {
+    pub entry: ClipboardEntry,
+    pub pinned: bool,
+    /// Unix timestamp after which the entry is deleted, e.g. as it looks like a secret.
+    pub expires_at: Option<u64>,
+    /// Sorted alphabetically.
+    pub tags: Vec<String>,
+    /// Additional representations, in the order they were stored.
+    pub parts: Vec<EntryPart>,
 }
 
 impl<'stmt> TryFrom<&Row<'stmt>> for ClipboardEntry {
@@ -14,6 +47,7 @@ impl<'stmt> TryFrom<&Row<'stmt>> for ClipboardEntry {
             id: row.get(0)?,
             content: row.get(1)?,
             last_updated: row.get(2)?,
+            mime_type: row.get(3)?,
         })
     }
 }
@@ -29,3 +63,252 @@ impl PartialOrd for ClipboardEntry {
         Some(self.cmp(other))
     }
 }
+
+/// The general kind of content of an entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EntryKind {
+    /// UTF-8 text.
+    Text,
+    /// Image data in a format which could be read.
+    Image,
+    /// Any other binary data.
+    Binary,
+    /// Text in an encoding other than UTF-8, e.g. UTF-16.
+    NonUtf8Text,
+}
+
+impl EntryKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Text => "text",
+            Self::Image => "image",
+            Self::Binary => "binary",
+            Self::NonUtf8Text => "non-utf8-text",
+        }
+    }
+}
+
+impl ToSql for EntryKind {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(self.as_str().into())
+    }
+}
+
+impl FromSql for EntryKind {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value.as_str()? {
+            "text" => Ok(Self::Text),
+            "image" => Ok(Self::Image),
+            "binary" => Ok(Self::Binary),
+            "non-utf8-text" => Ok(Self::NonUtf8Text),
+            other => Err(FromSqlError::Other(
+                format!("unknown entry kind: {other}").into(),
+            )),
+        }
+    }
+}
+
+/// Which entries may be deleted when pruning entries which are too old or over the limit.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct PruneOptions {
+    /// Also delete pinned entries.
+    pub include_pinned: bool,
+    /// Keep entries with at least one tag.
+    pub keep_tagged: bool,
+}
+
+/// A pause of capturing entries, during which `store` doesn't record anything.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pause {
+    /// Unix timestamp at which the pause ends, or `None` to pause until resumed.
+    pub until: Option<u64>,
+}
+
+/// Conditions for the entries to return when listing, where `None` means no condition.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct EntryFilter {
+    /// Only pinned entries.
+    pub pinned: bool,
+    /// Only entries with the given tag.
+    pub tag: Option<String>,
+    /// Order pinned entries before all others.
+    pub pinned_first: bool,
+    pub kind: Option<EntryKind>,
+    /// Glob pattern for the MIME type, e.g. `image/*`.
+    pub mime_type: Option<String>,
+    pub min_size: Option<usize>,
+    pub max_size: Option<usize>,
+    /// Unix timestamp - only entries updated at or after this time.
+    pub since: Option<u64>,
+    /// Unix timestamp - only entries updated at or before this time.
+    pub until: Option<u64>,
+    pub limit: Option<usize>,
+    pub offset: usize,
+}
+
+/// Metadata about the content of an entry, computed when the entry is stored so that listing
+/// entries does not require reading or decoding their content.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntryMetadata {
+    pub kind: EntryKind,
+    /// Length of the content in bytes.
+    pub size: usize,
+    /// Pixel dimensions, only for images.
+    pub dimensions: Option<(u32, u32)>,
+    /// Text with collapsed whitespace, limited to [`defaults::MAX_STORED_PREVIEW_LEN`]
+    /// characters. Only for UTF-8 text.
+    pub preview: Option<String>,
+}
+
+impl EntryMetadata {
+    pub fn from_content(data: &[u8]) -> Self {
+        let size = data.len();
+
+        match content_inspector::inspect(data) {
+            ContentType::BINARY => match image_dimensions(data) {
+                Some(dimensions) => Self {
+                    kind: EntryKind::Image,
+                    size,
+                    dimensions: Some(dimensions),
+                    preview: None,
+                },
+                None => Self {
+                    kind: EntryKind::Binary,
+                    size,
+                    dimensions: None,
+                    preview: None,
+                },
+            },
+            ContentType::UTF_8 | ContentType::UTF_8_BOM => Self {
+                kind: EntryKind::Text,
+                size,
+                dimensions: None,
+                preview: Some(preview_text(data, defaults::MAX_STORED_PREVIEW_LEN)),
+            },
+            _ => Self {
+                kind: EntryKind::NonUtf8Text,
+                size,
+                dimensions: None,
+                preview: None,
+            },
+        }
+    }
+}
+
+/// BLAKE3 hash of the content of an entry, used to detect duplicates.
+pub fn content_hash(content: &[u8]) -> [u8; 32] {
+    *blake3::hash(content).as_bytes()
+}
+
+/// Dimensions of the given image data, if it is in a supported image format.
+fn image_dimensions(data: &[u8]) -> Option<(u32, u32)> {
+    ImageReader::new(Cursor::new(data))
+        .with_guessed_format()
+        .ok()?
+        .into_dimensions()
+        .ok()
+}
+
+/// Text with all whitespace collapsed to single spaces, truncated to the given width.
+fn preview_text(data: &[u8], width: usize) -> String {
+    // Stop early for large inputs - graphemes can consist of multiple characters, so leave room
+    let max_bytes = width.saturating_mul(16);
+
+    let mut result = String::new();
+    for w in String::from_utf8_lossy(data).split_whitespace() {
+        if !result.is_empty() {
+            result.push(' ');
+        }
+        result.push_str(w);
+
+        if result.len() > max_bytes {
+            break;
+        }
+    }
+
+    truncate(&result, width).into_owned()
+}
+
+/// An entry without its content, as used for listing entries.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntrySummary {
+    pub id: u64,
+    pub last_updated: u64,
+    pub mime_type: Option<String>,
+    pub pinned: bool,
+    /// Sorted alphabetically.
+    pub tags: Vec<String>,
+    /// Name of the snippet, if this is a snippet rather than an entry in the history. The ID is
+    /// then only unique among snippets.
+    pub snippet: Option<String>,
+    /// Hex-encoded hash of the content, the same for identical entries in any database. `None` for
+    /// snippets.
+    pub hash: Option<String>,
+    pub metadata: EntryMetadata,
+}
+
+impl<'stmt> TryFrom<&Row<'stmt>> for EntrySummary {
+    type Error = rusqlite::Error;
+    fn try_from(row: &Row) -> std::result::Result<Self, Self::Error> {
+        let width: Option<u32> = row.get(5)?;
+        let height: Option<u32> = row.get(6)?;
+        let tags: Option<String> = row.get(9)?;
+
+        Ok(Self {
+            id: row.get(0)?,
+            last_updated: row.get(1)?,
+            mime_type: row.get(2)?,
+            pinned: row.get(8)?,
+            tags: tags
+                .map(|t| t.split(',').map(str::to_owned).collect())
+                .unwrap_or_default(),
+            snippet: None,
+            hash: row.get(10)?,
+            metadata: EntryMetadata {
+                kind: row.get(3)?,
+                size: row.get(4)?,
+                dimensions: width.zip(height),
+                preview: row.get(7)?,
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_metadata_text() {
+        let metadata = EntryMetadata::from_content(b"  hello\n\tworld  ");
+        assert_eq!(metadata.kind, EntryKind::Text);
+        assert_eq!(metadata.size, 16);
+        assert_eq!(metadata.dimensions, None);
+        assert_eq!(metadata.preview.as_deref(), Some("hello world"));
+
+        let long = "word ".repeat(defaults::MAX_STORED_PREVIEW_LEN * 10);
+        let metadata = EntryMetadata::from_content(long.as_bytes());
+        assert_eq!(
+            metadata.preview.map(|p| p.chars().count()),
+            Some(defaults::MAX_STORED_PREVIEW_LEN)
+        );
+    }
+
+    #[test]
+    fn test_metadata_binary() {
+        // 1x1 GIF
+        let gif = b"GIF89a\x01\x00\x01\x00\x80\x01\x00\xff\xff\xff\x00\x00\x00\x21\xf9\x04\x01\x0a\x00\x01\x00\x2c\x00\x00\x00\x00\x01\x00\x01\x00\x00\x02\x02\x4c\x01\x00\x3b";
+        let metadata = EntryMetadata::from_content(gif);
+        assert_eq!(metadata.kind, EntryKind::Image);
+        assert_eq!(metadata.dimensions, Some((1, 1)));
+        assert_eq!(metadata.preview, None);
+
+        let metadata = EntryMetadata::from_content(b"\x00\x01\x02\xff\xfe");
+        assert_eq!(metadata.kind, EntryKind::Binary);
+        assert_eq!(metadata.size, 5);
+        assert_eq!(metadata.dimensions, None);
+    }
+}
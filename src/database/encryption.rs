@@ -0,0 +1,404 @@
+//! Optional encryption of the content of entries.
+//!
+//! Content is encrypted with XChaCha20-Poly1305, using a key derived from a passphrase or key file
+//! with Argon2id. The nonce is derived from a keyed hash of the plaintext (a synthetic IV), so
+//! encryption is deterministic - the same content always results in the same ciphertext, allowing
+//! duplicate entries to still be detected by the unique index on the (encrypted) content hash.
+//!
+//! Queries access the content through the `encrypt` and `decrypt` SQL functions, which are
+//! registered on every connection and simply return their argument if encryption is disabled.
+
+use std::{
+    path::Path,
+    sync::{Arc, OnceLock},
+};
+
+use argon2::Argon2;
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+use chacha20poly1305::{
+    KeyInit, XChaCha20Poly1305, XNonce,
+    aead::{Aead, AeadCore, generic_array::typenum::Unsigned},
+};
+use miette::{Context, IntoDiagnostic, Result, miette};
+use rusqlite::{
+    Connection,
+    functions::FunctionFlags,
+    types::{Value, ValueRef},
+};
+
+use super::queries::{
+    checkpoint, count_all_entries, delete_encryption, get_attached_encryption, get_encryption,
+    set_encryption, vacuum,
+};
+
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+
+/// Encrypted and stored alongside the salt, to check that the correct key was given.
+const KEY_CHECK: &[u8] = b"clipvault";
+
+/// Secret given by the user, set once at startup.
+static SECRET: OnceLock<Vec<u8>> = OnceLock::new();
+
+/// Cipher derived from [`SECRET`] - cached, as deriving the key is deliberately slow.
+static CIPHER: OnceLock<Arc<Cipher>> = OnceLock::new();
+
+/// Deterministic authenticated encryption, with a key derived from a secret.
+pub struct Cipher {
+    aead: XChaCha20Poly1305,
+    nonce_key: [u8; 32],
+}
+
+impl Cipher {
+    /// Derive the keys from the secret using Argon2id.
+    pub fn derive(secret: &[u8], salt: &[u8]) -> Result<Self> {
+        let mut key = [0; 32];
+        Argon2::default()
+            .hash_password_into(secret, salt, &mut key)
+            .map_err(|e| miette!("failed to derive key: {e}"))?;
+
+        let aead_key = blake3::derive_key("clipvault content encryption", &key);
+        let nonce_key = blake3::derive_key("clipvault content nonce", &key);
+
+        Ok(Self {
+            aead: XChaCha20Poly1305::new(&aead_key.into()),
+            nonce_key,
+        })
+    }
+
+    /// Encrypt the data, with the nonce prepended.
+    pub fn encrypt(&self, plaintext: &[u8]) -> Vec<u8> {
+        let hash = blake3::keyed_hash(&self.nonce_key, plaintext);
+        let nonce = XNonce::from_slice(&hash.as_bytes()[..NONCE_LEN]);
+
+        let mut data = nonce.to_vec();
+        data.extend(
+            self.aead
+                .encrypt(nonce, plaintext)
+                .expect("encryption should only fail for huge inputs"),
+        );
+        data
+    }
+
+    /// Decrypt data created by [`Self::encrypt`]. Returns `None` if the data was encrypted with a
+    /// different key, or was modified.
+    pub fn decrypt(&self, data: &[u8]) -> Option<Vec<u8>> {
+        if data.len() < NONCE_LEN + <XChaCha20Poly1305 as AeadCore>::TagSize::USIZE {
+            return None;
+        }
+
+        let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+        self.aead
+            .decrypt(XNonce::from_slice(nonce), ciphertext)
+            .ok()
+    }
+}
+
+/// Read the secret from the key file or passphrase, if either is given.
+pub fn read_secret(key_file: Option<&Path>, passphrase: Option<&str>) -> Result<Option<Vec<u8>>> {
+    let secret = match (key_file, passphrase) {
+        (Some(path), _) => std::fs::read(path)
+            .into_diagnostic()
+            .with_context(|| format!("failed to read key file: {}", path.display()))?,
+        (None, Some(passphrase)) => passphrase.as_bytes().to_vec(),
+        (None, None) => return Ok(None),
+    };
+
+    if secret.is_empty() {
+        return Err(miette!("the key file or passphrase must not be empty"));
+    }
+
+    Ok(Some(secret))
+}
+
+/// Set the secret used to encrypt and decrypt entries for every connection.
+pub fn set_secret(secret: Vec<u8>) {
+    if SECRET.set(secret).is_err() {
+        tracing::warn!("secret was already set");
+    }
+}
+
+/// Register the `encrypt` and `decrypt` SQL functions, using the given ciphers. If a cipher is
+/// not given, the function returns its argument unchanged.
+///
+/// Blobs are encrypted to blobs, and text to base64-encoded text, so that the types of the
+/// columns are preserved.
+fn register_functions(
+    conn: &Connection,
+    encrypt: Option<Arc<Cipher>>,
+    decrypt: Option<Arc<Cipher>>,
+) -> Result<()> {
+    let flags = FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC;
+
+    conn.create_scalar_function("encrypt", 1, flags, move |ctx| {
+        let Some(cipher) = &encrypt else {
+            return ctx.get::<Value>(0);
+        };
+
+        Ok(match ctx.get_raw(0) {
+            ValueRef::Blob(b) => Value::Blob(cipher.encrypt(b)),
+            ValueRef::Text(t) => Value::Text(BASE64.encode(cipher.encrypt(t))),
+            value => value.into(),
+        })
+    })
+    .into_diagnostic()
+    .context("failed to register function: encrypt")?;
+
+    register_decrypt(conn, "decrypt", decrypt)
+}
+
+/// Register a SQL function with the given name which decrypts its argument with the cipher, as
+/// for the `decrypt` function registered by [`register_functions`].
+fn register_decrypt(conn: &Connection, name: &str, cipher: Option<Arc<Cipher>>) -> Result<()> {
+    let flags = FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC;
+
+    conn.create_scalar_function(name, 1, flags, move |ctx| {
+        let Some(cipher) = &cipher else {
+            return ctx.get::<Value>(0);
+        };
+
+        let decrypt = |data: &[u8]| {
+            cipher.decrypt(data).ok_or_else(|| {
+                rusqlite::Error::UserFunctionError("failed to decrypt content".into())
+            })
+        };
+
+        Ok(match ctx.get_raw(0) {
+            ValueRef::Blob(b) => Value::Blob(decrypt(b)?),
+            ValueRef::Text(t) => {
+                let data = BASE64
+                    .decode(t)
+                    .map_err(|e| rusqlite::Error::UserFunctionError(e.into()))?;
+                Value::Text(
+                    String::from_utf8(decrypt(&data)?)
+                        .map_err(|e| rusqlite::Error::UserFunctionError(e.into()))?,
+                )
+            }
+            value => value.into(),
+        })
+    })
+    .into_diagnostic()
+    .with_context(|| format!("failed to register function: {name}"))
+}
+
+/// Derive the cipher for the database from the secret, and check it is the correct key.
+fn unlock(secret: &[u8], salt: &[u8], key_check: &[u8]) -> Result<Cipher> {
+    let cipher = Cipher::derive(secret, salt)?;
+    if cipher.decrypt(key_check).as_deref() != Some(KEY_CHECK) {
+        return Err(miette!("incorrect key for the encrypted database"));
+    }
+
+    Ok(cipher)
+}
+
+/// Generate a new salt, and derive a cipher from the secret using it.
+fn new_cipher(secret: &[u8]) -> Result<(Vec<u8>, Cipher)> {
+    let mut salt = vec![0; SALT_LEN];
+    getrandom::fill(&mut salt).map_err(|e| miette!("failed to generate salt: {e}"))?;
+    let cipher = Cipher::derive(secret, &salt)?;
+
+    Ok((salt, cipher))
+}
+
+/// Get the cipher for an encrypted database from the secret, checking that it is the correct key.
+fn existing_cipher(salt: &[u8], key_check: &[u8]) -> Result<Arc<Cipher>> {
+    // Each database has its own salt, so the cached cipher only works for one of them
+    if let Some(cipher) = CIPHER.get()
+        && cipher.decrypt(key_check).as_deref() == Some(KEY_CHECK)
+    {
+        return Ok(cipher.clone());
+    }
+
+    let Some(secret) = SECRET.get() else {
+        return Err(miette!(
+            help = "provide the key using --key-file, CLIPVAULT_KEY_FILE or CLIPVAULT_PASSPHRASE",
+            "the database is encrypted, but no key was given"
+        ));
+    };
+
+    let cipher = Arc::new(unlock(secret, salt, key_check)?);
+    let _ = CIPHER.set(cipher.clone());
+    Ok(cipher)
+}
+
+/// Check that the secret, if any, matches the encryption state of the database, and register the
+/// SQL functions used to access encrypted content.
+///
+/// An empty database is encrypted automatically if a secret is given.
+#[tracing::instrument(skip(conn))]
+pub(super) fn setup(conn: &Connection) -> Result<()> {
+    let cipher = match (get_encryption(conn)?, SECRET.get()) {
+        (None, None) => None,
+        (Some((salt, key_check)), _) => Some(existing_cipher(&salt, &key_check)?),
+        (None, Some(secret)) => {
+            if count_all_entries(conn)? > 0 {
+                return Err(miette!(
+                    help = "run `clipvault encrypt` to encrypt the existing entries",
+                    "a key was given, but the database is not encrypted"
+                ));
+            }
+
+            tracing::info!("encrypting empty database");
+            let (salt, cipher) = new_cipher(secret)?;
+            set_encryption(conn, &salt, &cipher.encrypt(KEY_CHECK))?;
+            Some(CIPHER.get_or_init(|| Arc::new(cipher)).clone())
+        }
+    };
+
+    register_functions(conn, cipher.clone(), cipher)
+}
+
+/// Register the SQL functions for the current encryption state of the database, without
+/// encrypting an empty database. Used by migrations which need to access the content.
+pub(super) fn setup_existing(conn: &Connection) -> Result<()> {
+    let cipher = match get_encryption(conn)? {
+        Some((salt, key_check)) => Some(existing_cipher(&salt, &key_check)?),
+        None => None,
+    };
+
+    register_functions(conn, cipher.clone(), cipher)
+}
+
+/// Register the `decrypt_attached` SQL function, used to read the content of the database attached
+/// for merging. It must either be unencrypted, or encrypted with the same secret.
+pub(super) fn setup_attached(conn: &Connection) -> Result<()> {
+    let cipher = match get_attached_encryption(conn)? {
+        Some((salt, key_check)) => Some(existing_cipher(&salt, &key_check)?),
+        None => None,
+    };
+
+    register_decrypt(conn, "decrypt_attached", cipher)
+}
+
+/// Replace every encrypted value in the database, decrypting with the first cipher and then
+/// encrypting with the second, and store the new salt and key check.
+fn reencrypt(
+    conn: &mut Connection,
+    from: Option<Cipher>,
+    to: Option<(Vec<u8>, Cipher)>,
+) -> Result<()> {
+    let (encryption, to) = to.unzip();
+    let encrypted = encryption.is_some();
+    register_functions(conn, to.map(Arc::new), from.map(Arc::new))?;
+
+    let tx = conn
+        .transaction()
+        .into_diagnostic()
+        .context("failed to start transaction")?;
+
+    // Update the state first, so that search triggers only index decrypted content
+    match encryption {
+        Some(salt) => {
+            let key_check = tx
+                .query_one("SELECT encrypt(?)", [KEY_CHECK], |row| {
+                    row.get::<_, Vec<u8>>(0)
+                })
+                .into_diagnostic()
+                .context("failed to encrypt key check")?;
+            set_encryption(&tx, &salt, &key_check)?;
+        }
+        None => delete_encryption(&tx)?,
+    }
+
+    tx.execute_batch(include_str!("./queries/reencrypt.sql"))
+        .into_diagnostic()
+        .context("failed to execute: re-encrypt entries")?;
+
+    // Encrypted content is never indexed, and the tokens of the plaintext would otherwise remain
+    let (index_query, index_action) = if encrypted {
+        (include_str!("./queries/clear_search_index.sql"), "clear")
+    } else {
+        (
+            include_str!("./queries/rebuild_search_index.sql"),
+            "rebuild",
+        )
+    };
+    tx.execute_batch(index_query)
+        .into_diagnostic()
+        .with_context(|| format!("failed to execute: {index_action} search index"))?;
+    tx.commit()
+        .into_diagnostic()
+        .context("failed to commit transaction")?;
+
+    // Remove any leftover copies of the previous content
+    vacuum(conn)?;
+    checkpoint(conn)
+}
+
+/// Encrypt the content of every entry in an unencrypted database.
+#[tracing::instrument(skip_all)]
+pub fn encrypt_db(conn: &mut Connection, secret: &[u8]) -> Result<()> {
+    if get_encryption(conn)?.is_some() {
+        return Err(miette!(
+            help = "use `clipvault rekey` to change the key",
+            "the database is already encrypted"
+        ));
+    }
+
+    reencrypt(conn, None, Some(new_cipher(secret)?))
+}
+
+/// Decrypt the content of every entry in an encrypted database.
+#[tracing::instrument(skip_all)]
+pub fn decrypt_db(conn: &mut Connection, secret: &[u8]) -> Result<()> {
+    let Some((salt, key_check)) = get_encryption(conn)? else {
+        return Err(miette!("the database is not encrypted"));
+    };
+
+    reencrypt(conn, Some(unlock(secret, &salt, &key_check)?), None)
+}
+
+/// Re-encrypt the content of every entry in an encrypted database with a new key.
+#[tracing::instrument(skip_all)]
+pub fn rekey_db(conn: &mut Connection, secret: &[u8], new_secret: &[u8]) -> Result<()> {
+    let Some((salt, key_check)) = get_encryption(conn)? else {
+        return Err(miette!(
+            help = "use `clipvault encrypt` to encrypt it",
+            "the database is not encrypted"
+        ));
+    };
+
+    reencrypt(
+        conn,
+        Some(unlock(secret, &salt, &key_check)?),
+        Some(new_cipher(new_secret)?),
+    )
+}
+
+/// Register the SQL functions for an unencrypted database, e.g. for tests.
+#[cfg(test)]
+pub(super) fn setup_unencrypted(conn: &Connection) -> Result<()> {
+    register_functions(conn, None, None)
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_cipher() {
+        let cipher = Cipher::derive(b"secret", b"0123456789abcdef").unwrap();
+
+        let data = cipher.encrypt(b"some content");
+        assert_ne!(&data[NONCE_LEN..], b"some content");
+        assert_eq!(cipher.decrypt(&data).as_deref(), Some(&b"some content"[..]));
+
+        // Deterministic
+        assert_eq!(cipher.encrypt(b"some content"), data);
+        assert_ne!(cipher.encrypt(b"other content"), data);
+
+        // Modified data
+        let mut modified = data.clone();
+        *modified.last_mut().unwrap() ^= 1;
+        assert_eq!(cipher.decrypt(&modified), None);
+        assert_eq!(cipher.decrypt(&data[..NONCE_LEN]), None);
+
+        // Different key or salt
+        let other = Cipher::derive(b"secret2", b"0123456789abcdef").unwrap();
+        assert_eq!(other.decrypt(&data), None);
+        let other = Cipher::derive(b"secret", b"fedcba9876543210").unwrap();
+        assert_eq!(other.decrypt(&data), None);
+    }
+}
@@ -0,0 +1 @@
+ALTER TABLE clipboard ADD COLUMN mime_type text;
@@ -0,0 +1 @@
+DROP TABLE IF EXISTS clipboard_part;
@@ -0,0 +1,7 @@
+CREATE TABLE IF NOT EXISTS clipboard_part
+(
+    entry_id integer NOT NULL REFERENCES clipboard (id) ON DELETE CASCADE,
+    mime_type text NOT NULL,
+    content blob NOT NULL,
+    PRIMARY KEY (entry_id, mime_type)
+) STRICT ;
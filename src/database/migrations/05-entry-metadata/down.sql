@@ -0,0 +1,5 @@
+ALTER TABLE clipboard DROP COLUMN preview;
+ALTER TABLE clipboard DROP COLUMN height;
+ALTER TABLE clipboard DROP COLUMN width;
+ALTER TABLE clipboard DROP COLUMN size;
+ALTER TABLE clipboard DROP COLUMN kind;
@@ -0,0 +1,5 @@
+ALTER TABLE clipboard ADD COLUMN kind text NOT NULL DEFAULT 'binary';
+ALTER TABLE clipboard ADD COLUMN size integer NOT NULL DEFAULT 0;
+ALTER TABLE clipboard ADD COLUMN width integer;
+ALTER TABLE clipboard ADD COLUMN height integer;
+ALTER TABLE clipboard ADD COLUMN preview text;
@@ -0,0 +1,27 @@
+-- Contentless full-text index of text entries, using the ID of each entry as the rowid
+CREATE VIRTUAL TABLE IF NOT EXISTS clipboard_fts USING fts5 (
+    content,
+    content = '',
+    contentless_delete = 1
+);
+
+INSERT INTO clipboard_fts (rowid, content)
+SELECT id, CAST(content AS text) FROM clipboard WHERE kind = 'text';
+
+CREATE TRIGGER IF NOT EXISTS clipboard_fts_insert AFTER INSERT ON clipboard
+WHEN new.kind = 'text'
+BEGIN
+    INSERT INTO clipboard_fts (rowid, content) VALUES (new.id, CAST(new.content AS text));
+END;
+
+CREATE TRIGGER IF NOT EXISTS clipboard_fts_update AFTER UPDATE OF content, kind ON clipboard
+BEGIN
+    DELETE FROM clipboard_fts WHERE rowid = old.id;
+    INSERT INTO clipboard_fts (rowid, content)
+    SELECT new.id, CAST(new.content AS text) WHERE new.kind = 'text';
+END;
+
+CREATE TRIGGER IF NOT EXISTS clipboard_fts_delete AFTER DELETE ON clipboard
+BEGIN
+    DELETE FROM clipboard_fts WHERE rowid = old.id;
+END;
@@ -0,0 +1 @@
+ALTER TABLE clipboard DROP COLUMN pinned;
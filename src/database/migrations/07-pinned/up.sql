@@ -0,0 +1,2 @@
+-- Pinned entries are not removed when pruning or clearing the history
+ALTER TABLE clipboard ADD COLUMN pinned integer NOT NULL DEFAULT 0;
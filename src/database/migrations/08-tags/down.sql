@@ -0,0 +1,4 @@
+DROP TRIGGER IF EXISTS clipboard_tag_delete;
+DROP INDEX IF EXISTS clipboard_tag_tag_id;
+DROP TABLE IF EXISTS clipboard_tag;
+DROP TABLE IF EXISTS tag;
@@ -0,0 +1,21 @@
+CREATE TABLE IF NOT EXISTS tag
+(
+    id integer PRIMARY KEY,
+    name text NOT NULL UNIQUE
+) STRICT ;
+
+CREATE TABLE IF NOT EXISTS clipboard_tag
+(
+    entry_id integer NOT NULL REFERENCES clipboard (id) ON DELETE CASCADE,
+    tag_id integer NOT NULL REFERENCES tag (id) ON DELETE CASCADE,
+    PRIMARY KEY (entry_id, tag_id)
+) STRICT ;
+
+CREATE INDEX IF NOT EXISTS clipboard_tag_tag_id ON clipboard_tag (tag_id);
+
+-- Tags only exist while at least one entry has them
+CREATE TRIGGER IF NOT EXISTS clipboard_tag_delete AFTER DELETE ON clipboard_tag
+WHEN NOT EXISTS (SELECT 1 FROM clipboard_tag WHERE tag_id = old.tag_id)
+BEGIN
+    DELETE FROM tag WHERE id = old.tag_id;
+END;
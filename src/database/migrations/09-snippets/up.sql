@@ -0,0 +1,14 @@
+-- Named entries which are kept until explicitly removed, separate from the clipboard history
+CREATE TABLE IF NOT EXISTS snippet
+(
+    id integer PRIMARY KEY,
+    name text NOT NULL UNIQUE,
+    content blob NOT NULL,
+    last_updated integer NOT NULL,
+    mime_type text NOT NULL,
+    kind text NOT NULL,
+    size integer NOT NULL,
+    width integer,
+    height integer,
+    preview text
+) STRICT ;
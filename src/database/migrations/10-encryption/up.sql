@@ -0,0 +1,24 @@
+-- Only contains a row if the content of entries is encrypted
+CREATE TABLE IF NOT EXISTS encryption
+(
+    id integer PRIMARY KEY CHECK (id = 1),
+    salt blob NOT NULL,
+    key_check blob NOT NULL
+) STRICT ;
+
+-- Encrypted content can't be indexed for search
+DROP TRIGGER IF EXISTS clipboard_fts_insert;
+CREATE TRIGGER IF NOT EXISTS clipboard_fts_insert AFTER INSERT ON clipboard
+WHEN new.kind = 'text' AND NOT EXISTS (SELECT 1 FROM encryption)
+BEGIN
+    INSERT INTO clipboard_fts (rowid, content) VALUES (new.id, CAST(new.content AS text));
+END;
+
+DROP TRIGGER IF EXISTS clipboard_fts_update;
+CREATE TRIGGER IF NOT EXISTS clipboard_fts_update AFTER UPDATE OF content, kind ON clipboard
+BEGIN
+    DELETE FROM clipboard_fts WHERE rowid = old.id;
+    INSERT INTO clipboard_fts (rowid, content)
+    SELECT new.id, CAST(new.content AS text)
+    WHERE new.kind = 'text' AND NOT EXISTS (SELECT 1 FROM encryption);
+END;
@@ -0,0 +1,19 @@
+UPDATE clipboard SET content = encrypt(decompress(decrypt(content), codec)) WHERE codec IS NOT NULL;
+
+DROP TRIGGER IF EXISTS clipboard_fts_insert;
+CREATE TRIGGER IF NOT EXISTS clipboard_fts_insert AFTER INSERT ON clipboard
+WHEN new.kind = 'text' AND NOT EXISTS (SELECT 1 FROM encryption)
+BEGIN
+    INSERT INTO clipboard_fts (rowid, content) VALUES (new.id, CAST(new.content AS text));
+END;
+
+DROP TRIGGER IF EXISTS clipboard_fts_update;
+CREATE TRIGGER IF NOT EXISTS clipboard_fts_update AFTER UPDATE OF content, kind ON clipboard
+BEGIN
+    DELETE FROM clipboard_fts WHERE rowid = old.id;
+    INSERT INTO clipboard_fts (rowid, content)
+    SELECT new.id, CAST(new.content AS text)
+    WHERE new.kind = 'text' AND NOT EXISTS (SELECT 1 FROM encryption);
+END;
+
+ALTER TABLE clipboard DROP COLUMN codec;
@@ -0,0 +1,20 @@
+-- Codec used to compress the content of the entry, or NULL if it is stored as-is
+ALTER TABLE clipboard ADD COLUMN codec text;
+
+-- Index the decompressed content
+DROP TRIGGER IF EXISTS clipboard_fts_insert;
+CREATE TRIGGER IF NOT EXISTS clipboard_fts_insert AFTER INSERT ON clipboard
+WHEN new.kind = 'text' AND NOT EXISTS (SELECT 1 FROM encryption)
+BEGIN
+    INSERT INTO clipboard_fts (rowid, content)
+    VALUES (new.id, CAST(decompress(new.content, new.codec) AS text));
+END;
+
+DROP TRIGGER IF EXISTS clipboard_fts_update;
+CREATE TRIGGER IF NOT EXISTS clipboard_fts_update AFTER UPDATE OF content, kind ON clipboard
+BEGIN
+    DELETE FROM clipboard_fts WHERE rowid = old.id;
+    INSERT INTO clipboard_fts (rowid, content)
+    SELECT new.id, CAST(decompress(new.content, new.codec) AS text)
+    WHERE new.kind = 'text' AND NOT EXISTS (SELECT 1 FROM encryption);
+END;
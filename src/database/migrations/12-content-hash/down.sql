@@ -0,0 +1,43 @@
+CREATE TABLE clipboard_old
+(
+    id integer PRIMARY KEY,
+    content blob NOT NULL UNIQUE,
+    last_updated integer NOT NULL,
+    mime_type text,
+    kind text NOT NULL DEFAULT 'binary',
+    size integer NOT NULL DEFAULT 0,
+    width integer,
+    height integer,
+    preview text,
+    pinned integer NOT NULL DEFAULT 0,
+    codec text
+) STRICT ;
+
+INSERT INTO clipboard_old (id, content, last_updated, mime_type, kind, size, width, height, preview, pinned, codec)
+SELECT id, content, last_updated, mime_type, kind, size, width, height, preview, pinned, codec
+FROM clipboard;
+
+DROP TABLE clipboard;
+ALTER TABLE clipboard_old RENAME TO clipboard;
+
+CREATE INDEX IF NOT EXISTS last_updated ON clipboard (last_updated);
+
+CREATE TRIGGER IF NOT EXISTS clipboard_fts_insert AFTER INSERT ON clipboard
+WHEN new.kind = 'text' AND NOT EXISTS (SELECT 1 FROM encryption)
+BEGIN
+    INSERT INTO clipboard_fts (rowid, content)
+    VALUES (new.id, CAST(decompress(new.content, new.codec) AS text));
+END;
+
+CREATE TRIGGER IF NOT EXISTS clipboard_fts_update AFTER UPDATE OF content, kind ON clipboard
+BEGIN
+    DELETE FROM clipboard_fts WHERE rowid = old.id;
+    INSERT INTO clipboard_fts (rowid, content)
+    SELECT new.id, CAST(decompress(new.content, new.codec) AS text)
+    WHERE new.kind = 'text' AND NOT EXISTS (SELECT 1 FROM encryption);
+END;
+
+CREATE TRIGGER IF NOT EXISTS clipboard_fts_delete AFTER DELETE ON clipboard
+BEGIN
+    DELETE FROM clipboard_fts WHERE rowid = old.id;
+END;
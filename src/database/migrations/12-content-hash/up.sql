@@ -0,0 +1,48 @@
+-- Detect duplicate entries by a hash of their content, rather than an index over the content
+-- itself. The hashes of existing entries are filled in by a hook after this migration.
+CREATE TABLE clipboard_new
+(
+    id integer PRIMARY KEY,
+    content blob NOT NULL,
+    last_updated integer NOT NULL,
+    mime_type text,
+    kind text NOT NULL DEFAULT 'binary',
+    size integer NOT NULL DEFAULT 0,
+    width integer,
+    height integer,
+    preview text,
+    pinned integer NOT NULL DEFAULT 0,
+    codec text,
+    hash blob
+) STRICT ;
+
+INSERT INTO clipboard_new (id, content, last_updated, mime_type, kind, size, width, height, preview, pinned, codec)
+SELECT id, content, last_updated, mime_type, kind, size, width, height, preview, pinned, codec
+FROM clipboard;
+
+-- Foreign keys are disabled while migrating, so entries referencing the table are kept
+DROP TABLE clipboard;
+ALTER TABLE clipboard_new RENAME TO clipboard;
+
+CREATE UNIQUE INDEX IF NOT EXISTS clipboard_hash ON clipboard (hash);
+CREATE INDEX IF NOT EXISTS last_updated ON clipboard (last_updated);
+
+CREATE TRIGGER IF NOT EXISTS clipboard_fts_insert AFTER INSERT ON clipboard
+WHEN new.kind = 'text' AND NOT EXISTS (SELECT 1 FROM encryption)
+BEGIN
+    INSERT INTO clipboard_fts (rowid, content)
+    VALUES (new.id, CAST(decompress(new.content, new.codec) AS text));
+END;
+
+CREATE TRIGGER IF NOT EXISTS clipboard_fts_update AFTER UPDATE OF content, kind ON clipboard
+BEGIN
+    DELETE FROM clipboard_fts WHERE rowid = old.id;
+    INSERT INTO clipboard_fts (rowid, content)
+    SELECT new.id, CAST(decompress(new.content, new.codec) AS text)
+    WHERE new.kind = 'text' AND NOT EXISTS (SELECT 1 FROM encryption);
+END;
+
+CREATE TRIGGER IF NOT EXISTS clipboard_fts_delete AFTER DELETE ON clipboard
+BEGIN
+    DELETE FROM clipboard_fts WHERE rowid = old.id;
+END;
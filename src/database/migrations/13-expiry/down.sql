@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS expires_at;
+ALTER TABLE clipboard DROP COLUMN expires_at;
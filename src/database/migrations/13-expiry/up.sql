@@ -0,0 +1,5 @@
+-- Unix timestamp after which the entry is deleted, e.g. for entries which look like secrets. NULL
+-- means the entry only expires with the rest of the history.
+ALTER TABLE clipboard ADD COLUMN expires_at integer;
+
+CREATE INDEX IF NOT EXISTS expires_at ON clipboard (expires_at) WHERE expires_at IS NOT NULL;
@@ -0,0 +1,7 @@
+-- Only contains a row while capture is paused, in which case `store` doesn't record anything until
+-- the given Unix timestamp, or until resumed if it is NULL
+CREATE TABLE IF NOT EXISTS pause
+(
+    id integer PRIMARY KEY CHECK (id = 1),
+    until integer
+) STRICT ;
@@ -1,44 +1,259 @@
-use std::{path::Path, sync::LazyLock};
+use std::{
+    path::Path,
+    sync::{LazyLock, OnceLock},
+    time::Duration,
+};
 
 use include_dir::{Dir, include_dir};
-use miette::{Context, IntoDiagnostic, Result};
-use rusqlite::Connection;
-use rusqlite_migration::Migrations;
+use miette::{Context, IntoDiagnostic, Result, miette};
+use rusqlite::{Connection, OpenFlags, Transaction, TransactionBehavior};
+use rusqlite_migration::{HookError, HookResult, Migrations, MigrationsBuilder};
 use tracing::instrument;
 
+use crate::defaults;
+
+mod compression;
 pub mod data;
+pub mod encryption;
 pub mod queries;
 
 // DB MIGRATIONS, DEFINED IN ./migrations
 static MIGRATIONS_DIR: Dir = include_dir!("$CARGO_MANIFEST_DIR/src/database/migrations");
-static MIGRATIONS: LazyLock<Migrations<'static>> =
-    LazyLock::new(|| Migrations::from_directory(&MIGRATIONS_DIR).unwrap());
+static MIGRATIONS: LazyLock<Migrations<'static>> = LazyLock::new(|| {
+    MigrationsBuilder::from_directory(&MIGRATIONS_DIR)
+        .unwrap()
+        .edit(5, |m| m.set_up_hook(backfill_metadata))
+        .edit(12, |m| m.set_up_hook(backfill_hashes).foreign_key_check())
+        .finalize()
+});
+
+/// Compute metadata for entries stored before it was recorded.
+fn backfill_metadata(tx: &rusqlite::Transaction) -> HookResult {
+    queries::update_all_metadata(tx).map_err(|e| HookError::Hook(e.to_string()))
+}
+
+/// Compute the content hash of entries stored before it was used to detect duplicates.
+fn backfill_hashes(tx: &rusqlite::Transaction) -> HookResult {
+    compression::setup(tx)
+        .and_then(|()| encryption::setup_existing(tx))
+        .and_then(|()| queries::update_all_hashes(tx))
+        .map_err(|e| HookError::Hook(e.to_string()))
+}
+
+/// Time to wait for locks held by other connections, set once at startup.
+static BUSY_TIMEOUT: OnceLock<Duration> = OnceLock::new();
+
+/// Set how long every connection waits for locks held by other connections before failing.
+pub fn set_busy_timeout(timeout: Duration) {
+    if BUSY_TIMEOUT.set(timeout).is_err() {
+        tracing::warn!("busy timeout was already set");
+    }
+}
+
+fn connect(path_db: &Path) -> Result<Connection> {
+    let conn = Connection::open(path_db)
+        .into_diagnostic()
+        .context("failed to connect to the database")?;
+
+    let timeout = BUSY_TIMEOUT.get_or_init(|| {
+        humantime::parse_duration(defaults::BUSY_TIMEOUT).expect("default should be valid")
+    });
+    conn.busy_timeout(*timeout)
+        .into_diagnostic()
+        .context("failed to set busy timeout")?;
+
+    compression::setup(&conn)?;
+    Ok(conn)
+}
 
 /// Get a database connection. Make sure the DB is initialised somewhere first
 /// before calling this function.
 pub fn get_db_connection(path_db: &Path) -> Result<Connection> {
-    Connection::open(path_db)
-        .into_diagnostic()
-        .context("failed to connect to the database")
+    let conn = connect(path_db)?;
+    encryption::setup(&conn)?;
+    Ok(conn)
 }
 
 #[instrument]
-/// Initialise the database, creating the file and applying migrations if needed
+/// Initialise the database, creating the file and applying migrations if needed, and delete any
+/// expired entries so that they are never seen
 pub fn init_db(path_db: &Path) -> Result<Connection> {
+    let conn = init_db_without_encryption(path_db)?;
+    encryption::setup(&conn)?;
+    queries::purge_expired_entries(&conn)?;
+    Ok(conn)
+}
+
+/// Make sure the DB uses incremental auto-vacuum, so that space freed by deleting entries can be
+/// cheaply reclaimed with [`queries::reclaim_space`].
+///
+/// For a new DB, this only needs to be set before any tables are created. Existing DBs are
+/// rebuilt once with a full `VACUUM` for the change to take effect.
+fn enable_incremental_vacuum(conn: &Connection) -> Result<()> {
+    const INCREMENTAL: u8 = 2;
+
+    let mode: u8 = conn
+        .pragma_query_value(None, "auto_vacuum", |row| row.get(0))
+        .into_diagnostic()
+        .context("failed to query PRAGMA: auto vacuum")?;
+    if mode == INCREMENTAL {
+        return Ok(());
+    }
+
+    tracing::debug!("enabling incremental auto-vacuum");
+    conn.pragma_update(None, "auto_vacuum", "INCREMENTAL")
+        .into_diagnostic()
+        .context("failed to apply PRAGMA: auto vacuum")?;
+
+    let has_tables: bool = conn
+        .query_one("SELECT EXISTS (SELECT 1 FROM sqlite_schema)", [], |row| {
+            row.get(0)
+        })
+        .into_diagnostic()
+        .context("failed to query: schema")?;
+    if has_tables {
+        queries::vacuum(conn)?;
+    }
+
+    Ok(())
+}
+
+/// Start a transaction which takes the write lock immediately, waiting up to the busy timeout for
+/// other connections to finish.
+pub fn write_transaction(conn: &mut Connection) -> Result<Transaction<'_>> {
+    let tx = conn
+        .transaction_with_behavior(TransactionBehavior::Immediate)
+        .into_diagnostic()
+        .context("failed to start transaction")?;
+
+    // A `VACUUM` by another connection changes the schema. Reload it now, as preparing a deletion
+    // from a table with both foreign keys and triggers on a virtual table using an outdated schema
+    // can otherwise fail with "no such table".
+    tx.query_one("SELECT COUNT(*) FROM sqlite_schema", [], |_| Ok(()))
+        .into_diagnostic()
+        .context("failed to load schema")?;
+
+    Ok(tx)
+}
+
+/// A database attached by [`attach_merge_source`], which must be kept until it is detached.
+pub struct MergeSource {
+    /// Upgraded copy of the database, if it used an older schema.
+    _copy: Option<tempfile::TempPath>,
+}
+
+/// Attach another database to the connection read-only to merge its entries, and register the
+/// functions used to access its content.
+///
+/// The database is never modified. If it uses an older schema, a temporary copy is upgraded and
+/// attached instead. Must be called outside of a transaction.
+pub fn attach_merge_source(conn: &Connection, path: &Path) -> Result<MergeSource> {
+    let source = Connection::open_with_flags(
+        path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )
+    .into_diagnostic()
+    .context("failed to open the database to merge")?;
+    let version = MIGRATIONS
+        .current_version(&source)
+        .into_diagnostic()
+        .context("failed to get the version of the database to merge")?;
+    let latest = MIGRATIONS
+        .current_version(conn)
+        .into_diagnostic()
+        .context("failed to get the version of the database")?;
+
+    let copy = if version == latest {
+        None
+    } else if version > latest {
+        return Err(miette!(
+            help = "update clipvault to merge it",
+            "the database to merge was created by a newer version of clipvault"
+        ));
+    } else {
+        // Migrations can only be applied to the main database of a connection
+        tracing::debug!("upgrading a copy of the database to merge");
+        let copy = tempfile::NamedTempFile::new()
+            .into_diagnostic()
+            .context("failed to create temporary file")?
+            .into_temp_path();
+        queries::copy_database(&source, utf8_path(&copy)?)?;
+        drop(init_db_without_encryption(&copy)?);
+        Some(copy)
+    };
+    drop(source);
+
+    let attached = copy.as_deref().unwrap_or(path);
+    queries::attach_merge_source(conn, &format!("{}?mode=ro", file_uri(utf8_path(attached)?)))?;
+    encryption::setup_attached(conn)?;
+
+    Ok(MergeSource { _copy: copy })
+}
+
+/// Detach the database attached by [`attach_merge_source`], deleting any temporary copy.
+pub fn detach_merge_source(conn: &Connection, source: MergeSource) -> Result<()> {
+    queries::detach_merge_source(conn)?;
+    drop(source);
+    Ok(())
+}
+
+fn utf8_path(path: &Path) -> Result<&str> {
+    path.to_str()
+        .ok_or_else(|| miette!("path of the database to merge is not valid UTF-8"))
+}
+
+/// URI for the file at the given path, escaping the characters with special meanings in URIs.
+fn file_uri(path: &str) -> String {
+    path.chars().fold(String::from("file:"), |mut uri, c| {
+        match c {
+            '%' => uri.push_str("%25"),
+            '?' => uri.push_str("%3F"),
+            '#' => uri.push_str("%23"),
+            _ => uri.push(c),
+        }
+        uri
+    })
+}
+
+/// Initialise the database like [`init_db`], but without checking the key or registering the
+/// functions used to access encrypted content. Only used for managing encryption.
+#[instrument]
+pub fn init_db_without_encryption(path_db: &Path) -> Result<Connection> {
     tracing::debug!("initialising DB");
-    let mut conn = get_db_connection(path_db)?;
+    let mut conn = connect(path_db)?;
 
     tracing::trace!("applying PRAGMA");
+    // Must come first, as switching the journal mode already initialises a new DB
+    enable_incremental_vacuum(&conn)?;
     conn.pragma_update(None, "journal_mode", "WAL")
         .into_diagnostic()
         .context("failed to apply PRAGMA: journal mode")?;
 
+    // Foreign keys are enabled by default, but must be disabled while applying migrations so that
+    // tables can be recreated without deleting the rows which reference them
+    conn.pragma_update(None, "foreign_keys", "OFF")
+        .into_diagnostic()
+        .context("failed to apply PRAGMA: foreign keys")?;
+
     tracing::trace!("applying migrations");
+    let version = MIGRATIONS
+        .current_version(&conn)
+        .into_diagnostic()
+        .context("failed to get the version of the database")?;
     MIGRATIONS
         .to_latest(&mut conn)
         .into_diagnostic()
         .context("failed to apply migrations")?;
 
+    // Migrations which recreate tables leave behind free pages
+    if version != MIGRATIONS.current_version(&conn).into_diagnostic()? {
+        queries::reclaim_space(&conn)?;
+    }
+
+    conn.pragma_update(None, "foreign_keys", "ON")
+        .into_diagnostic()
+        .context("failed to apply PRAGMA: foreign keys")?;
+
     Ok(conn)
 }
 
@@ -51,4 +266,63 @@ mod tests {
         assert!(MIGRATIONS.validate().is_ok());
         insta::assert_debug_snapshot!(MIGRATIONS);
     }
+
+    #[test]
+    fn test_backfill_metadata() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        compression::setup(&conn).unwrap();
+        MIGRATIONS.to_version(&mut conn, 4).unwrap();
+        conn.execute_batch(
+            "INSERT INTO clipboard (content, last_updated) VALUES (CAST('  some\n text ' AS BLOB), 1);
+             INSERT INTO clipboard (content, last_updated) VALUES (X'00010203FFFE', 2);",
+        )
+        .unwrap();
+
+        MIGRATIONS.to_latest(&mut conn).unwrap();
+        encryption::setup_unencrypted(&conn).unwrap();
+        let entries = queries::get_entries(&conn, &data::EntryFilter::default()).unwrap();
+
+        assert_eq!(entries[0].metadata.kind, data::EntryKind::Binary);
+        assert_eq!(entries[0].metadata.size, 6);
+        assert_eq!(entries[1].metadata.kind, data::EntryKind::Text);
+        assert_eq!(entries[1].metadata.preview.as_deref(), Some("some text"));
+    }
+
+    #[test]
+    fn test_backfill_hashes() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.pragma_update(None, "foreign_keys", "OFF").unwrap();
+        compression::setup(&conn).unwrap();
+        MIGRATIONS.to_version(&mut conn, 11).unwrap();
+        conn.execute_batch(
+            "INSERT INTO clipboard (content, last_updated, kind) VALUES (CAST('text' AS BLOB), 1, 'text');
+             INSERT INTO clipboard_part (entry_id, mime_type, content) VALUES (1, 'text/html', X'00');
+             INSERT INTO tag (name) VALUES ('tag');
+             INSERT INTO clipboard_tag (entry_id, tag_id) VALUES (1, 1);",
+        )
+        .unwrap();
+
+        MIGRATIONS.to_latest(&mut conn).unwrap();
+        encryption::setup_unencrypted(&conn).unwrap();
+        let entries = queries::get_entries(&conn, &data::EntryFilter::default()).unwrap();
+        assert_eq!(
+            entries[0].hash.as_deref(),
+            Some(blake3::hash(b"text").to_hex().as_str())
+        );
+        assert_eq!(entries[0].tags, ["tag"]);
+        assert!(
+            queries::get_entry_part(&conn, 1, "text/html")
+                .unwrap()
+                .is_some()
+        );
+
+        // Still searchable, and duplicates are still detected
+        let results =
+            queries::search_entries(&conn, "text", &data::EntryFilter::default(), true).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(
+            queries::upsert_entry(&conn, b"text", "text/plain", None).unwrap(),
+            1
+        );
+    }
 }
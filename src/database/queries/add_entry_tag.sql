@@ -0,0 +1 @@
+INSERT OR IGNORE INTO clipboard_tag (entry_id, tag_id) SELECT ?, id FROM tag WHERE name = ?
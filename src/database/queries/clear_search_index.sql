@@ -0,0 +1,3 @@
+-- Remove every token from the contentless search index, including those of deleted entries which
+-- are otherwise only dropped when the index is merged
+INSERT INTO clipboard_fts (clipboard_fts) VALUES ('delete-all');
@@ -0,0 +1 @@
+SELECT COUNT(*) FROM merge_source.clipboard
@@ -0,0 +1,4 @@
+SELECT COUNT(*)
+FROM clipboard
+WHERE (?1 OR NOT pinned)
+    AND (NOT ?2 OR id NOT IN (SELECT entry_id FROM clipboard_tag))
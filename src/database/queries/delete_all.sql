@@ -1 +1 @@
-DELETE FROM clipboard;
+DELETE FROM clipboard WHERE ? OR NOT pinned;
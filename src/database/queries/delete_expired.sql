@@ -0,0 +1 @@
+DELETE FROM clipboard WHERE expires_at < ?
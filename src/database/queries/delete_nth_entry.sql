@@ -1,4 +1,4 @@
 DELETE
 FROM clipboard
 WHERE
-    id = (SELECT id FROM clipboard ORDER BY last_updated DESC LIMIT 1 OFFSET ?)
+    id = (SELECT id FROM clipboard ORDER BY last_updated DESC, id DESC LIMIT 1 OFFSET ?)
@@ -1 +1,4 @@
-DELETE FROM clipboard WHERE last_updated < ?
+DELETE FROM clipboard
+WHERE last_updated < ?1
+    AND (?2 OR NOT pinned)
+    AND (NOT ?3 OR id NOT IN (SELECT entry_id FROM clipboard_tag))
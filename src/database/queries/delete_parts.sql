@@ -0,0 +1 @@
+DELETE FROM clipboard_part WHERE entry_id = ?
@@ -0,0 +1 @@
+DELETE FROM snippet WHERE name = ?
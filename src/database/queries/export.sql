@@ -0,0 +1,28 @@
+SELECT id, decompress(decrypt(content), codec), last_updated, mime_type, pinned, expires_at,
+    (
+        SELECT group_concat(t.name, ',' ORDER BY t.name)
+        FROM clipboard_tag AS ct
+        JOIN tag AS t ON t.id = ct.tag_id
+        WHERE ct.entry_id = clipboard.id
+    ) AS tags
+FROM clipboard
+WHERE id IN (
+    SELECT id
+    FROM clipboard
+    WHERE (:kind IS NULL OR kind = :kind)
+        AND (:mime_type IS NULL OR mime_type GLOB :mime_type)
+        AND (:min_size IS NULL OR size >= :min_size)
+        AND (:max_size IS NULL OR size <= :max_size)
+        AND (:since IS NULL OR last_updated >= :since)
+        AND (:until IS NULL OR last_updated <= :until)
+        AND (NOT :pinned OR pinned)
+        AND (:tag IS NULL OR id IN (
+            SELECT ct.entry_id
+            FROM clipboard_tag AS ct
+            JOIN tag AS t ON t.id = ct.tag_id
+            WHERE t.name = :tag
+        ))
+    ORDER BY last_updated DESC, id DESC
+    LIMIT coalesce(:limit, -1) OFFSET :offset
+)
+ORDER BY last_updated, id
@@ -1,3 +1,24 @@
-SELECT id, substr (content, 1, ?) AS content, last_updated
+SELECT id, last_updated, mime_type, kind, size, width, height, decrypt(preview), pinned,
+    (
+        SELECT group_concat(t.name, ',' ORDER BY t.name)
+        FROM clipboard_tag AS ct
+        JOIN tag AS t ON t.id = ct.tag_id
+        WHERE ct.entry_id = clipboard.id
+    ) AS tags,
+    lower(hex(decrypt(hash))) AS hash
 FROM clipboard
-ORDER BY last_updated DESC
+WHERE (:kind IS NULL OR kind = :kind)
+    AND (:mime_type IS NULL OR mime_type GLOB :mime_type)
+    AND (:min_size IS NULL OR size >= :min_size)
+    AND (:max_size IS NULL OR size <= :max_size)
+    AND (:since IS NULL OR last_updated >= :since)
+    AND (:until IS NULL OR last_updated <= :until)
+    AND (NOT :pinned OR pinned)
+    AND (:tag IS NULL OR id IN (
+        SELECT ct.entry_id
+        FROM clipboard_tag AS ct
+        JOIN tag AS t ON t.id = ct.tag_id
+        WHERE t.name = :tag
+    ))
+ORDER BY (:pinned_first AND pinned) DESC, last_updated DESC, id DESC
+LIMIT coalesce(:limit, -1) OFFSET :offset
@@ -0,0 +1 @@
+SELECT id, decompress(decrypt(content), codec) FROM clipboard
@@ -0,0 +1 @@
+SELECT salt, key_check FROM merge_source.encryption WHERE id = 1
@@ -0,0 +1 @@
+SELECT content FROM clipboard WHERE id = ?
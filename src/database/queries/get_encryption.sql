@@ -0,0 +1 @@
+SELECT salt, key_check FROM encryption WHERE id = 1
@@ -1 +1 @@
-SELECT * FROM clipboard WHERE id = ?
+SELECT id, decompress(decrypt(content), codec), last_updated, mime_type FROM clipboard WHERE id = ?
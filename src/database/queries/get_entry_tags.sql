@@ -0,0 +1,5 @@
+SELECT t.name
+FROM clipboard_tag AS ct
+JOIN tag AS t ON t.id = ct.tag_id
+WHERE ct.entry_id = ?
+ORDER BY t.name
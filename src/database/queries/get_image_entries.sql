@@ -0,0 +1 @@
+SELECT id, hash FROM clipboard WHERE kind = 'image' ORDER BY last_updated DESC, id DESC
@@ -1 +1 @@
-SELECT * FROM clipboard ORDER BY last_updated DESC LIMIT 1 OFFSET ?
+SELECT id, decompress(decrypt(content), codec), last_updated, mime_type FROM clipboard ORDER BY last_updated DESC, id DESC LIMIT 1 OFFSET ?
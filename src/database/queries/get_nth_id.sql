@@ -0,0 +1 @@
+SELECT id FROM clipboard ORDER BY last_updated DESC, id DESC LIMIT 1 OFFSET ?
@@ -0,0 +1 @@
+SELECT decrypt(content) FROM clipboard_part WHERE entry_id = ? AND mime_type = ?
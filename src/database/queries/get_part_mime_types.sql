@@ -0,0 +1 @@
+SELECT mime_type FROM clipboard_part WHERE entry_id = ? ORDER BY rowid
@@ -0,0 +1 @@
+SELECT mime_type, decrypt(content) FROM clipboard_part WHERE entry_id = ? ORDER BY rowid
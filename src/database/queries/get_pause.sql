@@ -0,0 +1 @@
+SELECT until FROM pause WHERE id = 1 AND (until IS NULL OR until > ?)
@@ -0,0 +1 @@
+SELECT id, decrypt(content), last_updated, mime_type FROM snippet WHERE name = ?
@@ -0,0 +1,3 @@
+SELECT id, last_updated, mime_type, kind, size, width, height, decrypt(preview), FALSE, NULL, NULL, name
+FROM snippet
+ORDER BY name
@@ -0,0 +1,5 @@
+SELECT t.name, COUNT(*)
+FROM tag AS t
+JOIN clipboard_tag AS ct ON ct.tag_id = t.id
+GROUP BY t.id
+ORDER BY t.name
@@ -0,0 +1,3 @@
+INSERT
+INTO clipboard_part (entry_id, mime_type, content)
+VALUES (?, ?, encrypt(?))
@@ -0,0 +1 @@
+INSERT INTO tag (name) VALUES (?) ON CONFLICT (name) DO NOTHING
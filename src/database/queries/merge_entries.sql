@@ -0,0 +1,36 @@
+-- Copy every entry from the attached database, re-encrypting its values. Content stays compressed
+-- with the same codec. Entries with the same content are merged, keeping the newest timestamp.
+INSERT
+INTO main.clipboard (content, codec, hash, last_updated, mime_type, kind, size, width, height, preview, pinned,
+                    expires_at)
+SELECT encrypt(decrypt_attached(content)), codec, encrypt(decrypt_attached(hash)), last_updated, mime_type,
+       kind, size, width, height, encrypt(decrypt_attached(preview)), pinned, expires_at
+FROM merge_source.clipboard
+WHERE true
+ON CONFLICT (hash) DO UPDATE SET
+    last_updated = max(last_updated, excluded.last_updated),
+    mime_type = iif(excluded.last_updated >= last_updated, excluded.mime_type, mime_type),
+    expires_at = iif(excluded.last_updated >= last_updated, excluded.expires_at, expires_at),
+    pinned = pinned OR excluded.pinned;
+
+-- Entries are matched up by their content hash
+INSERT OR IGNORE
+INTO main.clipboard_part (entry_id, mime_type, content)
+SELECT entry.id, part.mime_type, encrypt(decrypt_attached(part.content))
+FROM merge_source.clipboard_part AS part
+JOIN merge_source.clipboard AS other ON other.id = part.entry_id
+JOIN main.clipboard AS entry ON entry.hash = encrypt(decrypt_attached(other.hash));
+
+INSERT INTO main.tag (name)
+SELECT name FROM merge_source.tag
+WHERE true
+ON CONFLICT (name) DO NOTHING;
+
+INSERT OR IGNORE
+INTO main.clipboard_tag (entry_id, tag_id)
+SELECT entry.id, tag.id
+FROM merge_source.clipboard_tag AS other_tag
+JOIN merge_source.tag AS other_name ON other_name.id = other_tag.tag_id
+JOIN main.tag AS tag ON tag.name = other_name.name
+JOIN merge_source.clipboard AS other ON other.id = other_tag.entry_id
+JOIN main.clipboard AS entry ON entry.hash = encrypt(decrypt_attached(other.hash));
@@ -1,7 +1,18 @@
 use miette::{Context, IntoDiagnostic, Result, miette};
-use rusqlite::{Connection, fallible_iterator::FallibleIterator, params};
+use rusqlite::{
+    Connection, OptionalExtension, fallible_iterator::FallibleIterator, named_params, params,
+};
 
-use crate::{database::data::ClipboardEntry, utils::now};
+use crate::{
+    database::{
+        compression::compress,
+        data::{
+            ClipboardEntry, EntryFilter, EntryMetadata, EntryPart, EntrySummary, FullEntry, Pause,
+            PruneOptions, content_hash,
+        },
+    },
+    utils::now,
+};
 
 #[tracing::instrument(skip(conn))]
 pub fn count_entries(conn: &Connection) -> Result<usize> {
@@ -14,24 +25,33 @@ pub fn count_entries(conn: &Connection) -> Result<usize> {
     .context("failed to query: count of clipboard entries")
 }
 
+/// Get the entries matching the filter, most recently updated first.
 #[tracing::instrument(skip(conn))]
-pub fn get_all_entries(conn: &Connection, preview_width: usize) -> Result<Vec<ClipboardEntry>> {
-    tracing::debug!("getting all entries");
-
-    // NOTE: query truncates the blob content based on the allowed preview width (with a minimum
-    // for ensuring file signatures are present)
-    let max_blob_width = (preview_width.saturating_add(preview_width)).max(50);
+pub fn get_entries(conn: &Connection, filter: &EntryFilter) -> Result<Vec<EntrySummary>> {
+    tracing::debug!("getting entries");
 
     let mut stmt = conn
         .prepare(include_str!("./get_all.sql"))
         .into_diagnostic()
-        .context("failed to prepare: get all entries")?;
+        .context("failed to prepare: get entries")?;
 
-    let entries: Vec<ClipboardEntry> = stmt
-        .query(params![max_blob_width])
+    let entries: Vec<EntrySummary> = stmt
+        .query(named_params! {
+            ":kind": filter.kind,
+            ":mime_type": filter.mime_type,
+            ":min_size": filter.min_size,
+            ":max_size": filter.max_size,
+            ":since": filter.since,
+            ":until": filter.until,
+            ":pinned": filter.pinned,
+            ":tag": filter.tag,
+            ":pinned_first": filter.pinned_first,
+            ":limit": filter.limit,
+            ":offset": filter.offset,
+        })
         .into_diagnostic()
-        .context("failed to query: get all entries")?
-        .map(|c| ClipboardEntry::try_from(c))
+        .context("failed to query: get entries")?
+        .map(|c| EntrySummary::try_from(c))
         .collect()
         .into_diagnostic()
         .context("failed to create clipboard entries from database rows")?;
@@ -39,19 +59,214 @@ pub fn get_all_entries(conn: &Connection, preview_width: usize) -> Result<Vec<Cl
     Ok(entries)
 }
 
+/// Search the full text of text entries matching the filter using an FTS5 query, ordered by
+/// relevance or by most recently updated.
+#[tracing::instrument(skip(conn))]
+pub fn search_entries(
+    conn: &Connection,
+    query: &str,
+    filter: &EntryFilter,
+    by_rank: bool,
+) -> Result<Vec<EntrySummary>> {
+    tracing::debug!("searching entries");
+
+    let sql = if by_rank {
+        include_str!("./search.sql")
+    } else {
+        include_str!("./search_recent.sql")
+    };
+    let mut stmt = conn
+        .prepare(sql)
+        .into_diagnostic()
+        .context("failed to prepare: search entries")?;
+
+    let entries: Vec<EntrySummary> = stmt
+        .query(named_params! {
+            ":query": query,
+            ":kind": filter.kind,
+            ":mime_type": filter.mime_type,
+            ":min_size": filter.min_size,
+            ":max_size": filter.max_size,
+            ":since": filter.since,
+            ":until": filter.until,
+            ":pinned": filter.pinned,
+            ":tag": filter.tag,
+            ":pinned_first": filter.pinned_first,
+            ":limit": filter.limit,
+            ":offset": filter.offset,
+        })
+        .into_diagnostic()
+        .context("failed to query: search entries")?
+        .map(|c| EntrySummary::try_from(c))
+        .collect()
+        .into_diagnostic()
+        .context("failed to search entries - check the query syntax")?;
+
+    Ok(entries)
+}
+
+/// Call the function with every entry matching the filter, including its content, oldest first.
+///
+/// Entries are read one at a time, so that the whole history never needs to fit in memory. The
+/// ordering options of the filter are ignored, but `limit` and `offset` still count from the most
+/// recent entry.
+#[tracing::instrument(skip(conn, f))]
+pub fn for_each_full_entry(
+    conn: &Connection,
+    filter: &EntryFilter,
+    mut f: impl FnMut(FullEntry) -> Result<()>,
+) -> Result<()> {
+    tracing::debug!("reading full entries");
+
+    let mut stmt = conn
+        .prepare(include_str!("./export.sql"))
+        .into_diagnostic()
+        .context("failed to prepare: get full entries")?;
+    let mut get_parts = conn
+        .prepare(include_str!("./get_parts.sql"))
+        .into_diagnostic()
+        .context("failed to prepare: get entry parts")?;
+
+    let mut rows = stmt
+        .query(named_params! {
+            ":kind": filter.kind,
+            ":mime_type": filter.mime_type,
+            ":min_size": filter.min_size,
+            ":max_size": filter.max_size,
+            ":since": filter.since,
+            ":until": filter.until,
+            ":pinned": filter.pinned,
+            ":tag": filter.tag,
+            ":limit": filter.limit,
+            ":offset": filter.offset,
+        })
+        .into_diagnostic()
+        .context("failed to query: get full entries")?;
+    while let Some(row) = rows
+        .next()
+        .into_diagnostic()
+        .context("failed to read entry")?
+    {
+        let entry = ClipboardEntry::try_from(row)
+            .into_diagnostic()
+            .context("failed to create clipboard entry from database row")?;
+        let pinned: bool = row.get(4).into_diagnostic()?;
+        let expires_at: Option<u64> = row.get(5).into_diagnostic()?;
+        let tags: Option<String> = row.get(6).into_diagnostic()?;
+
+        let parts = get_parts
+            .query(params![entry.id])
+            .into_diagnostic()
+            .context("failed to query: get entry parts")?
+            .map(|row| {
+                Ok(EntryPart {
+                    mime_type: row.get(0)?,
+                    content: row.get(1)?,
+                })
+            })
+            .collect()
+            .into_diagnostic()
+            .context("failed to read entry parts")?;
+
+        f(FullEntry {
+            entry,
+            pinned,
+            expires_at,
+            tags: tags
+                .map(|t| t.split(',').map(str::to_owned).collect())
+                .unwrap_or_default(),
+            parts,
+        })?;
+    }
+
+    Ok(())
+}
+
+/// Get the ID and content hash of every image entry. The hashes are encrypted along with the
+/// content of encrypted databases.
+#[tracing::instrument(skip(conn))]
+pub fn get_image_entries(conn: &Connection) -> Result<Vec<(u64, Vec<u8>)>> {
+    tracing::debug!("getting image entries");
+
+    let mut stmt = conn
+        .prepare(include_str!("./get_image_entries.sql"))
+        .into_diagnostic()
+        .context("failed to prepare: get image entries")?;
+
+    let entries: Vec<(u64, Vec<u8>)> = stmt
+        .query(params![])
+        .into_diagnostic()
+        .context("failed to query: get image entries")?
+        .map(|row| Ok((row.get(0)?, row.get(1)?)))
+        .collect()
+        .into_diagnostic()
+        .context("failed to read image entries")?;
+
+    Ok(entries)
+}
+
+/// Recompute and store the metadata of every entry.
+#[tracing::instrument(skip(conn))]
+pub fn update_all_metadata(conn: &Connection) -> Result<()> {
+    tracing::debug!("updating metadata for all entries");
+
+    let ids: Vec<u64> = conn
+        .prepare(include_str!("./get_all_ids.sql"))
+        .and_then(|mut stmt| stmt.query(params![])?.map(|row| row.get(0)).collect())
+        .into_diagnostic()
+        .context("failed to query: get all entry IDs")?;
+
+    let mut get_content = conn
+        .prepare(include_str!("./get_content.sql"))
+        .into_diagnostic()
+        .context("failed to prepare: get entry content")?;
+    let mut update = conn
+        .prepare(include_str!("./update_metadata.sql"))
+        .into_diagnostic()
+        .context("failed to prepare: update entry metadata")?;
+
+    for id in ids {
+        let content: Vec<u8> = get_content
+            .query_one(params![id], |row| row.get(0))
+            .into_diagnostic()
+            .context("failed to query: get entry content")?;
+        let metadata = EntryMetadata::from_content(&content);
+        let (width, height) = metadata.dimensions.unzip();
+
+        update
+            .execute(params![
+                metadata.kind,
+                metadata.size,
+                width,
+                height,
+                metadata.preview,
+                id
+            ])
+            .into_diagnostic()
+            .context("failed to execute: update entry metadata")?;
+    }
+
+    Ok(())
+}
+
+/// Delete every entry, apart from pinned entries unless `include_pinned` is set.
 #[tracing::instrument(skip(conn))]
-pub fn delete_all_entries(conn: &Connection) -> Result<()> {
+pub fn delete_all_entries(conn: &Connection, include_pinned: bool) -> Result<()> {
     tracing::debug!("deleting all entries");
 
-    conn.execute(include_str!("./delete_all.sql"), params![])
-        .map(|_| ())
+    conn.execute(include_str!("./delete_all.sql"), params![include_pinned])
         .into_diagnostic()
-        .context("failed to execute: wipe entries")
+        .context("failed to execute: wipe entries")?;
+
+    reclaim_space(conn)
 }
 
-/// Perform a `VACUUM` on the DB, reducing its size by clearing deleted entries and defragmenting.
+/// Perform a `VACUUM` on the DB, rebuilding it to reduce its size as much as possible and
+/// defragment it. Rewrites the whole database, so is only done explicitly.
+///
+/// Can't be run inside a transaction.
 #[tracing::instrument(skip(conn))]
-fn vacuum(conn: &Connection) -> Result<()> {
+pub fn vacuum(conn: &Connection) -> Result<()> {
     tracing::debug!("vacuuming DB");
 
     conn.execute("VACUUM;", params![])
@@ -60,27 +275,122 @@ fn vacuum(conn: &Connection) -> Result<()> {
         .context("failed to execute: vacuum")
 }
 
+/// Return the pages freed by deleting entries to the file system, shrinking the DB file.
+///
+/// Unlike [`vacuum`], this is cheap and can be run inside a transaction, but requires the DB to
+/// use incremental auto-vacuum.
+#[tracing::instrument(skip(conn))]
+pub fn reclaim_space(conn: &Connection) -> Result<()> {
+    tracing::debug!("reclaiming free pages");
+
+    // Each step frees a single page, so it must run until completion
+    conn.prepare("PRAGMA incremental_vacuum;")
+        .and_then(|mut stmt| stmt.query(params![])?.map(|_| Ok(())).count())
+        .into_diagnostic()
+        .context("failed to execute: incremental vacuum")?;
+
+    Ok(())
+}
+
+/// Write all changes in the WAL to the DB file, and truncate the WAL.
+#[tracing::instrument(skip(conn))]
+pub fn checkpoint(conn: &Connection) -> Result<()> {
+    conn.pragma_update(None, "wal_checkpoint", "TRUNCATE")
+        .into_diagnostic()
+        .context("failed to checkpoint WAL")
+}
+
+/// Merge the segments of the full-text search index, making it smaller and faster to query.
 #[tracing::instrument(skip(conn))]
-pub fn delete_entries_older_than(conn: &Connection, timestamp: u64) -> Result<usize> {
+pub fn optimize_search_index(conn: &Connection) -> Result<()> {
+    conn.execute(include_str!("./optimize_search.sql"), params![])
+        .map(|_| ())
+        .into_diagnostic()
+        .context("failed to execute: optimize search index")
+}
+
+/// Get the size of the DB in bytes, excluding any changes which have not yet been checkpointed.
+#[tracing::instrument(skip(conn))]
+pub fn get_db_size(conn: &Connection) -> Result<usize> {
+    conn.query_one(
+        "SELECT page_count * page_size FROM pragma_page_count(), pragma_page_size()",
+        params![],
+        |row| row.get(0),
+    )
+    .into_diagnostic()
+    .context("failed to query: DB size")
+}
+
+/// Delete entries last updated before the timestamp, apart from those protected by the options.
+///
+/// Freed pages are not reclaimed, so that they only need to be reclaimed once after pruning.
+#[tracing::instrument(skip(conn))]
+pub fn delete_entries_older_than(
+    conn: &Connection,
+    timestamp: u64,
+    options: PruneOptions,
+) -> Result<usize> {
     tracing::debug!("deleting old entries");
 
-    let changed = conn
-        .execute(include_str!("./delete_old.sql"), params![timestamp])
+    conn.execute(
+        include_str!("./delete_old.sql"),
+        params![timestamp, options.include_pinned, options.keep_tagged],
+    )
+    .into_diagnostic()
+    .context("failed to execute: delete old entries")
+}
+
+/// Delete entries which expired before the timestamp. An expiry is always explicit, so it applies
+/// to pinned and tagged entries too.
+///
+/// Freed pages are not reclaimed, so that they only need to be reclaimed once after pruning.
+#[tracing::instrument(skip(conn))]
+pub fn delete_expired_entries(conn: &Connection, timestamp: u64) -> Result<usize> {
+    tracing::debug!("deleting expired entries");
+
+    conn.execute(include_str!("./delete_expired.sql"), params![timestamp])
         .into_diagnostic()
-        .context("failed to execute: delete old entries")?;
+        .context("failed to execute: delete expired entries")
+}
 
-    if changed > 0 {
-        vacuum(conn).map(|_| changed)
-    } else {
-        Ok(changed)
+/// Delete entries which have expired, and reclaim the freed pages. The write lock is only
+/// taken if there are any.
+#[tracing::instrument(skip(conn))]
+pub fn purge_expired_entries(conn: &Connection) -> Result<usize> {
+    let timestamp = now();
+    let any_expired: bool = conn
+        .query_one(
+            include_str!("./has_expired.sql"),
+            params![timestamp],
+            |row| row.get(0),
+        )
+        .into_diagnostic()
+        .context("failed to query: expired entries")?;
+    if !any_expired {
+        return Ok(0);
     }
+
+    let deleted = delete_expired_entries(conn, timestamp)?;
+    reclaim_space(conn)?;
+    Ok(deleted)
 }
 
+/// Delete the oldest entries so that no more than `limit` remain. Entries protected by the
+/// options are neither counted nor deleted.
+///
+/// Freed pages are not reclaimed, so that they only need to be reclaimed once after pruning.
 #[tracing::instrument(skip(conn))]
-pub fn trim_entries(conn: &Connection, limit: usize) -> Result<usize> {
+pub fn trim_entries(conn: &Connection, limit: usize, options: PruneOptions) -> Result<usize> {
     tracing::debug!("trimming entries over limit");
 
-    let count = count_entries(conn)?;
+    let count = conn
+        .query_one(
+            include_str!("./count_prunable.sql"),
+            params![options.include_pinned, options.keep_tagged],
+            |row| row.get::<usize, usize>(0),
+        )
+        .into_diagnostic()
+        .context("failed to query: count of prunable entries")?;
     if count <= limit {
         tracing::trace!("not over limit");
         return Ok(0);
@@ -88,7 +398,10 @@ pub fn trim_entries(conn: &Connection, limit: usize) -> Result<usize> {
 
     let del = count - limit;
     let changed = conn
-        .execute(include_str!("./trim_entries.sql"), params![del])
+        .execute(
+            include_str!("./trim_entries.sql"),
+            params![options.include_pinned, options.keep_tagged, del],
+        )
         .into_diagnostic()
         .context("failed to execute: trim clipboard entries")?;
     assert_eq!(
@@ -96,7 +409,7 @@ pub fn trim_entries(conn: &Connection, limit: usize) -> Result<usize> {
         "should only delete specified number of entries"
     );
 
-    vacuum(conn).map(|_| changed)
+    Ok(changed)
 }
 
 #[tracing::instrument(skip(conn))]
@@ -124,7 +437,7 @@ pub fn delete_entry_by_id(conn: &Connection, id: u64) -> Result<()> {
     }
     assert_eq!(changed, 1, "should only delete specified entry");
 
-    vacuum(conn)
+    reclaim_space(conn)
 }
 
 #[tracing::instrument(skip(conn))]
@@ -152,25 +465,567 @@ pub fn delete_entry_by_position(conn: &Connection, index: usize) -> Result<()> {
     }
     assert_eq!(changed, 1, "should only delete a single entry");
 
-    vacuum(conn)
+    reclaim_space(conn)
+}
+
+#[tracing::instrument(skip(conn))]
+pub fn set_pinned_by_id(conn: &Connection, id: u64, pinned: bool) -> Result<()> {
+    tracing::debug!("setting pinned state of entry by ID");
+
+    let changed = conn
+        .execute(include_str!("./set_pinned.sql"), params![pinned, id])
+        .into_diagnostic()
+        .context("failed to execute: set pinned state of entry")?;
+
+    if changed == 0 {
+        return Err(miette!("entry not found"));
+    }
+    assert_eq!(changed, 1, "should only update specified entry");
+
+    Ok(())
+}
+
+/// Set the timestamp after which the entry is deleted, or `None` to keep it until it is pruned.
+#[tracing::instrument(skip(conn))]
+pub fn set_expiry_by_id(conn: &Connection, id: u64, expires_at: Option<u64>) -> Result<()> {
+    tracing::debug!("setting expiry of entry by ID");
+
+    let changed = conn
+        .execute(include_str!("./set_expiry.sql"), params![expires_at, id])
+        .into_diagnostic()
+        .context("failed to execute: set expiry of entry")?;
+
+    if changed == 0 {
+        return Err(miette!("entry not found"));
+    }
+    assert_eq!(changed, 1, "should only update specified entry");
+
+    Ok(())
 }
 
+#[tracing::instrument(skip(conn))]
+pub fn set_pinned_by_position(conn: &Connection, index: usize, pinned: bool) -> Result<()> {
+    tracing::debug!("setting pinned state of entry by position");
+
+    let changed = conn
+        .execute(include_str!("./set_nth_pinned.sql"), params![pinned, index])
+        .into_diagnostic()
+        .context("couldn't set pinned state of entry by position")?;
+
+    if changed == 0 {
+        return Err(miette!("database is empty"));
+    }
+    assert_eq!(changed, 1, "should only update a single entry");
+
+    Ok(())
+}
+
+/// Compute and store the content hash of every entry.
+#[tracing::instrument(skip(conn))]
+pub fn update_all_hashes(conn: &Connection) -> Result<()> {
+    tracing::debug!("updating hashes for all entries");
+
+    let mut stmt = conn
+        .prepare(include_str!("./get_all_content.sql"))
+        .into_diagnostic()
+        .context("failed to prepare: get all entry content")?;
+    let mut update = conn
+        .prepare(include_str!("./update_hash.sql"))
+        .into_diagnostic()
+        .context("failed to prepare: update entry hash")?;
+
+    let mut rows = stmt
+        .query(params![])
+        .into_diagnostic()
+        .context("failed to query: get all entry content")?;
+    while let Some(row) = rows
+        .next()
+        .into_diagnostic()
+        .context("failed to read entry content")?
+    {
+        let id: u64 = row.get(0).into_diagnostic()?;
+        let content: Vec<u8> = row.get(1).into_diagnostic()?;
+
+        update
+            .execute(params![content_hash(&content), id])
+            .into_diagnostic()
+            .context("failed to execute: update entry hash")?;
+    }
+
+    Ok(())
+}
+
+/// Insert the entry, or bump it to the top if it already exists. Returns the ID of the entry.
+///
+/// The entry is deleted when pruning after the expiry timestamp, if given.
 #[tracing::instrument(skip_all)]
-pub fn upsert_entry(conn: &Connection, content: &[u8]) -> Result<()> {
+pub fn upsert_entry(
+    conn: &Connection,
+    content: &[u8],
+    mime_type: &str,
+    expires_at: Option<u64>,
+) -> Result<u64> {
+    upsert_entry_at(conn, content, mime_type, now(), expires_at)
+}
+
+/// Insert the entry as last updated at the given timestamp. If it already exists, the existing
+/// entry is only moved if the timestamp is more recent, and only then are its MIME type and expiry
+/// replaced. Returns the ID of the entry.
+#[tracing::instrument(skip(conn, content))]
+pub fn upsert_entry_at(
+    conn: &Connection,
+    content: &[u8],
+    mime_type: &str,
+    timestamp: u64,
+    expires_at: Option<u64>,
+) -> Result<u64> {
     tracing::debug!("creating entry");
     tracing::debug!(
         "entry content preview: {}",
         String::from_utf8_lossy(&content[..16.min(content.len())])
     );
 
-    let timestamp = now();
-    tracing::trace!("current_timestamp={timestamp}");
+    tracing::trace!("timestamp={timestamp}");
+    tracing::trace!("mime_type={mime_type}");
 
-    conn.execute(
+    let metadata = EntryMetadata::from_content(content);
+    tracing::trace!("metadata={metadata:?}");
+    let (width, height) = metadata.dimensions.unzip();
+    let (stored, codec) = compress(content, metadata.kind)?;
+    let hash = content_hash(content);
+
+    conn.query_one(
         include_str!("./upsert_post.sql"),
-        params![content, timestamp],
+        params![
+            stored,
+            codec,
+            hash,
+            timestamp,
+            mime_type,
+            metadata.kind,
+            metadata.size,
+            width,
+            height,
+            metadata.preview,
+            expires_at
+        ],
+        |row| row.get(0),
     )
-    .map(|_| ())
     .into_diagnostic()
     .context("failed to execute: upsert clipboard entry")
 }
+
+/// Replace the additional representations of an entry with the given ones.
+#[tracing::instrument(skip(conn, parts))]
+pub fn replace_entry_parts(conn: &Connection, id: u64, parts: &[EntryPart]) -> Result<()> {
+    tracing::debug!("replacing entry parts");
+
+    conn.execute(include_str!("./delete_parts.sql"), params![id])
+        .into_diagnostic()
+        .context("failed to execute: delete entry parts")?;
+
+    let mut stmt = conn
+        .prepare(include_str!("./insert_part.sql"))
+        .into_diagnostic()
+        .context("failed to prepare: insert entry part")?;
+
+    for part in parts {
+        tracing::trace!("inserting part: {}", part.mime_type);
+        stmt.execute(params![id, part.mime_type, part.content])
+            .into_diagnostic()
+            .context("failed to execute: insert entry part")?;
+    }
+
+    Ok(())
+}
+
+/// Get the content of an additional representation of an entry, if one exists for the given
+/// MIME type.
+#[tracing::instrument(skip(conn))]
+pub fn get_entry_part(conn: &Connection, id: u64, mime_type: &str) -> Result<Option<Vec<u8>>> {
+    tracing::debug!("getting entry part");
+
+    conn.query_one(
+        include_str!("./get_part.sql"),
+        params![id, mime_type],
+        |row| row.get(0),
+    )
+    .optional()
+    .into_diagnostic()
+    .context("failed to query: entry part")
+}
+
+/// Get the MIME types of the additional representations of an entry.
+#[tracing::instrument(skip(conn))]
+pub fn get_entry_part_mime_types(conn: &Connection, id: u64) -> Result<Vec<String>> {
+    tracing::debug!("getting entry part MIME types");
+
+    let mut stmt = conn
+        .prepare(include_str!("./get_part_mime_types.sql"))
+        .into_diagnostic()
+        .context("failed to prepare: get entry part MIME types")?;
+
+    let mime_types: Vec<String> = stmt
+        .query(params![id])
+        .into_diagnostic()
+        .context("failed to query: get entry part MIME types")?
+        .map(|row| row.get(0))
+        .collect()
+        .into_diagnostic()
+        .context("failed to read entry part MIME types")?;
+
+    Ok(mime_types)
+}
+
+/// Get the ID of the entry at the given position, ordered by most recently updated.
+#[tracing::instrument(skip(conn))]
+pub fn get_entry_id_by_position(conn: &Connection, index: usize) -> Result<u64> {
+    tracing::debug!("getting entry ID by position");
+
+    conn.query_one(include_str!("./get_nth_id.sql"), params![index], |row| {
+        row.get(0)
+    })
+    .into_diagnostic()
+    .context("couldn't get entry by position")
+}
+
+/// Whether an entry with the same content already exists.
+#[tracing::instrument(skip_all)]
+pub fn content_exists(conn: &Connection, content: &[u8]) -> Result<bool> {
+    conn.query_one(
+        include_str!("./hash_exists.sql"),
+        params![content_hash(content)],
+        |row| row.get(0),
+    )
+    .into_diagnostic()
+    .context("failed to query: content exists")
+}
+
+/// Get the number of entries in the database attached by [`super::attach_merge_source`].
+#[tracing::instrument(skip(conn))]
+pub fn count_attached_entries(conn: &Connection) -> Result<usize> {
+    conn.query_one(
+        include_str!("./count_attached_entries.sql"),
+        params![],
+        |row| row.get(0),
+    )
+    .into_diagnostic()
+    .context("failed to query: count of entries to merge")
+}
+
+/// Copy every entry with its parts, tags and pin from the database attached by
+/// [`super::attach_merge_source`]. Entries with the same content as an existing entry are merged
+/// into it, keeping the newest timestamp.
+#[tracing::instrument(skip(conn))]
+pub fn merge_attached_entries(conn: &Connection) -> Result<()> {
+    conn.execute_batch(include_str!("./merge_entries.sql"))
+        .into_diagnostic()
+        .context("failed to execute: merge entries")
+}
+
+#[tracing::instrument(skip(conn))]
+fn entry_exists(conn: &Connection, id: u64) -> Result<bool> {
+    conn.query_one(include_str!("./entry_exists.sql"), params![id], |row| {
+        row.get(0)
+    })
+    .into_diagnostic()
+    .context("failed to query: entry exists")
+}
+
+/// Add the tag to an entry, creating the tag if it doesn't exist yet.
+#[tracing::instrument(skip(conn))]
+pub fn add_entry_tag(conn: &Connection, id: u64, tag: &str) -> Result<()> {
+    tracing::debug!("adding tag to entry");
+
+    if !entry_exists(conn, id)? {
+        return Err(miette!("entry not found"));
+    }
+
+    conn.execute(include_str!("./insert_tag.sql"), params![tag])
+        .into_diagnostic()
+        .context("failed to execute: insert tag")?;
+    conn.execute(include_str!("./add_entry_tag.sql"), params![id, tag])
+        .map(|_| ())
+        .into_diagnostic()
+        .context("failed to execute: add tag to entry")
+}
+
+/// Remove the tag from an entry. Tags are deleted once no entries have them.
+#[tracing::instrument(skip(conn))]
+pub fn remove_entry_tag(conn: &Connection, id: u64, tag: &str) -> Result<()> {
+    tracing::debug!("removing tag from entry");
+
+    let changed = conn
+        .execute(include_str!("./remove_entry_tag.sql"), params![id, tag])
+        .into_diagnostic()
+        .context("failed to execute: remove tag from entry")?;
+
+    if changed == 0 {
+        return Err(miette!("entry {id} does not have the tag: {tag}"));
+    }
+
+    Ok(())
+}
+
+/// Get every tag in use, along with the number of entries which have it.
+#[tracing::instrument(skip(conn))]
+pub fn get_tags(conn: &Connection) -> Result<Vec<(String, usize)>> {
+    tracing::debug!("getting tags");
+
+    let mut stmt = conn
+        .prepare(include_str!("./get_tags.sql"))
+        .into_diagnostic()
+        .context("failed to prepare: get tags")?;
+
+    let tags = stmt
+        .query(params![])
+        .into_diagnostic()
+        .context("failed to query: get tags")?
+        .map(|row| Ok((row.get(0)?, row.get(1)?)))
+        .collect()
+        .into_diagnostic()
+        .context("failed to read tags")?;
+
+    Ok(tags)
+}
+
+/// Get the tags of an entry, sorted alphabetically.
+#[tracing::instrument(skip(conn))]
+pub fn get_entry_tags(conn: &Connection, id: u64) -> Result<Vec<String>> {
+    tracing::debug!("getting entry tags");
+
+    let mut stmt = conn
+        .prepare(include_str!("./get_entry_tags.sql"))
+        .into_diagnostic()
+        .context("failed to prepare: get entry tags")?;
+
+    let tags = stmt
+        .query(params![id])
+        .into_diagnostic()
+        .context("failed to query: get entry tags")?
+        .map(|row| row.get(0))
+        .collect()
+        .into_diagnostic()
+        .context("failed to read entry tags")?;
+
+    Ok(tags)
+}
+
+/// Save a snippet with the given name. An existing snippet with the same name is only replaced if
+/// `replace` is set, otherwise an error is returned.
+#[tracing::instrument(skip(conn, content))]
+pub fn upsert_snippet(
+    conn: &Connection,
+    name: &str,
+    content: &[u8],
+    mime_type: &str,
+    replace: bool,
+) -> Result<()> {
+    tracing::debug!("saving snippet");
+
+    let metadata = EntryMetadata::from_content(content);
+    tracing::trace!("metadata={metadata:?}");
+    let (width, height) = metadata.dimensions.unzip();
+
+    let changed = conn
+        .execute(
+            include_str!("./upsert_snippet.sql"),
+            named_params! {
+                ":name": name,
+                ":content": content,
+                ":last_updated": now(),
+                ":mime_type": mime_type,
+                ":kind": metadata.kind,
+                ":size": metadata.size,
+                ":width": width,
+                ":height": height,
+                ":preview": metadata.preview,
+                ":replace": replace,
+            },
+        )
+        .into_diagnostic()
+        .context("failed to execute: save snippet")?;
+
+    if changed == 0 {
+        return Err(miette!(
+            help = "use --force to replace it",
+            "snippet already exists: {name}"
+        ));
+    }
+
+    Ok(())
+}
+
+/// Get a snippet by name. The ID of the returned entry is only unique among snippets.
+#[tracing::instrument(skip(conn))]
+pub fn get_snippet(conn: &Connection, name: &str) -> Result<ClipboardEntry> {
+    tracing::debug!("getting snippet");
+
+    conn.query_one(include_str!("./get_snippet.sql"), params![name], |row| {
+        ClipboardEntry::try_from(row)
+    })
+    .optional()
+    .into_diagnostic()
+    .context("failed to query: get snippet")?
+    .ok_or_else(|| miette!("snippet not found: {name}"))
+}
+
+#[tracing::instrument(skip(conn))]
+pub fn delete_snippet(conn: &Connection, name: &str) -> Result<()> {
+    tracing::debug!("deleting snippet");
+
+    let changed = conn
+        .execute(include_str!("./delete_snippet.sql"), params![name])
+        .into_diagnostic()
+        .context("failed to execute: delete snippet")?;
+
+    if changed == 0 {
+        return Err(miette!("snippet not found: {name}"));
+    }
+
+    Ok(())
+}
+
+/// Get every snippet, ordered by name.
+#[tracing::instrument(skip(conn))]
+pub fn get_snippets(conn: &Connection) -> Result<Vec<EntrySummary>> {
+    tracing::debug!("getting snippets");
+
+    let mut stmt = conn
+        .prepare(include_str!("./get_snippets.sql"))
+        .into_diagnostic()
+        .context("failed to prepare: get snippets")?;
+
+    let snippets = stmt
+        .query(params![])
+        .into_diagnostic()
+        .context("failed to query: get snippets")?
+        .map(|row| {
+            Ok(EntrySummary {
+                snippet: Some(row.get(11)?),
+                ..EntrySummary::try_from(row)?
+            })
+        })
+        .collect()
+        .into_diagnostic()
+        .context("failed to create snippets from database rows")?;
+
+    Ok(snippets)
+}
+
+/// Get the total number of entries and snippets.
+#[tracing::instrument(skip(conn))]
+pub(super) fn count_all_entries(conn: &Connection) -> Result<usize> {
+    conn.query_one(
+        "SELECT (SELECT COUNT(*) FROM clipboard) + (SELECT COUNT(*) FROM snippet)",
+        params![],
+        |row| row.get(0),
+    )
+    .into_diagnostic()
+    .context("failed to query: count of all entries")
+}
+
+/// Get the salt and key check value, if the database is encrypted.
+#[tracing::instrument(skip(conn))]
+pub(super) fn get_encryption(conn: &Connection) -> Result<Option<(Vec<u8>, Vec<u8>)>> {
+    conn.query_one(include_str!("./get_encryption.sql"), params![], |row| {
+        Ok((row.get(0)?, row.get(1)?))
+    })
+    .optional()
+    .into_diagnostic()
+    .context("failed to query: encryption")
+}
+
+#[tracing::instrument(skip_all)]
+pub(super) fn set_encryption(conn: &Connection, salt: &[u8], key_check: &[u8]) -> Result<()> {
+    conn.execute(
+        include_str!("./set_encryption.sql"),
+        params![salt, key_check],
+    )
+    .map(|_| ())
+    .into_diagnostic()
+    .context("failed to execute: set encryption")
+}
+
+#[tracing::instrument(skip(conn))]
+pub(super) fn delete_encryption(conn: &Connection) -> Result<()> {
+    conn.execute(include_str!("./delete_encryption.sql"), params![])
+        .map(|_| ())
+        .into_diagnostic()
+        .context("failed to execute: delete encryption")
+}
+
+/// Whether the content of entries is encrypted.
+#[tracing::instrument(skip(conn))]
+pub fn is_encrypted(conn: &Connection) -> Result<bool> {
+    get_encryption(conn).map(|e| e.is_some())
+}
+
+/// Get the salt and key check value of the attached database, if it is encrypted.
+#[tracing::instrument(skip(conn))]
+pub(super) fn get_attached_encryption(conn: &Connection) -> Result<Option<(Vec<u8>, Vec<u8>)>> {
+    conn.query_one(
+        include_str!("./get_attached_encryption.sql"),
+        params![],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )
+    .optional()
+    .into_diagnostic()
+    .context("failed to query: encryption of the database to merge")
+}
+
+/// Attach the database with the given URI, e.g. `file:path?mode=ro`.
+#[tracing::instrument(skip(conn))]
+pub(super) fn attach_merge_source(conn: &Connection, uri: &str) -> Result<()> {
+    conn.execute(include_str!("./attach_merge_source.sql"), params![uri])
+        .map(|_| ())
+        .into_diagnostic()
+        .context("failed to attach the database to merge")
+}
+
+/// Write a consistent copy of the main database to the given path, which must not exist or be
+/// empty. Works for read-only connections.
+#[tracing::instrument(skip(conn))]
+pub(super) fn copy_database(conn: &Connection, path: &str) -> Result<()> {
+    conn.execute(include_str!("./copy_database.sql"), params![path])
+        .map(|_| ())
+        .into_diagnostic()
+        .context("failed to copy the database")
+}
+
+#[tracing::instrument(skip(conn))]
+pub(super) fn detach_merge_source(conn: &Connection) -> Result<()> {
+    conn.execute(include_str!("./detach_merge_source.sql"), params![])
+        .map(|_| ())
+        .into_diagnostic()
+        .context("failed to detach the database to merge")
+}
+
+/// Get the current pause of capturing entries, if any. Pauses which have ended are ignored.
+#[tracing::instrument(skip(conn))]
+pub fn get_pause(conn: &Connection) -> Result<Option<Pause>> {
+    conn.query_one(include_str!("./get_pause.sql"), params![now()], |row| {
+        Ok(Pause { until: row.get(0)? })
+    })
+    .optional()
+    .into_diagnostic()
+    .context("failed to query: pause")
+}
+
+/// Pause capturing entries, replacing any current pause.
+#[tracing::instrument(skip(conn))]
+pub fn set_pause(conn: &Connection, pause: Pause) -> Result<()> {
+    conn.execute(include_str!("./set_pause.sql"), params![pause.until])
+        .map(|_| ())
+        .into_diagnostic()
+        .context("failed to execute: set pause")
+}
+
+/// Resume capturing entries.
+#[tracing::instrument(skip(conn))]
+pub fn delete_pause(conn: &Connection) -> Result<()> {
+    conn.execute(include_str!("./delete_pause.sql"), params![])
+        .map(|_| ())
+        .into_diagnostic()
+        .context("failed to execute: delete pause")
+}
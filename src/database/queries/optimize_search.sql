@@ -0,0 +1 @@
+INSERT INTO clipboard_fts (clipboard_fts) VALUES ('optimize')
@@ -0,0 +1,4 @@
+-- Index every text entry from scratch, e.g. once the database is no longer encrypted
+INSERT INTO clipboard_fts (clipboard_fts) VALUES ('delete-all');
+INSERT INTO clipboard_fts (rowid, content)
+SELECT id, CAST(decompress(content, codec) AS text) FROM clipboard WHERE kind = 'text';
@@ -0,0 +1,5 @@
+-- Apply the current `decrypt` and then `encrypt` functions to all encrypted values
+UPDATE clipboard
+SET content = encrypt(decrypt(content)), preview = encrypt(decrypt(preview)), hash = encrypt(decrypt(hash));
+UPDATE clipboard_part SET content = encrypt(decrypt(content));
+UPDATE snippet SET content = encrypt(decrypt(content)), preview = encrypt(decrypt(preview));
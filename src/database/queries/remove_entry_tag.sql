@@ -0,0 +1,2 @@
+DELETE FROM clipboard_tag
+WHERE entry_id = ? AND tag_id = (SELECT id FROM tag WHERE name = ?)
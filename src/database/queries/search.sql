@@ -0,0 +1,26 @@
+SELECT c.id, c.last_updated, c.mime_type, c.kind, c.size, c.width, c.height, decrypt(c.preview), c.pinned,
+    (
+        SELECT group_concat(t.name, ',' ORDER BY t.name)
+        FROM clipboard_tag AS ct
+        JOIN tag AS t ON t.id = ct.tag_id
+        WHERE ct.entry_id = c.id
+    ) AS tags,
+    lower(hex(decrypt(c.hash))) AS hash
+FROM clipboard_fts
+JOIN clipboard AS c ON c.id = clipboard_fts.rowid
+WHERE clipboard_fts MATCH :query
+    AND (:kind IS NULL OR c.kind = :kind)
+    AND (:mime_type IS NULL OR c.mime_type GLOB :mime_type)
+    AND (:min_size IS NULL OR c.size >= :min_size)
+    AND (:max_size IS NULL OR c.size <= :max_size)
+    AND (:since IS NULL OR c.last_updated >= :since)
+    AND (:until IS NULL OR c.last_updated <= :until)
+    AND (NOT :pinned OR c.pinned)
+    AND (:tag IS NULL OR c.id IN (
+        SELECT ct.entry_id
+        FROM clipboard_tag AS ct
+        JOIN tag AS t ON t.id = ct.tag_id
+        WHERE t.name = :tag
+    ))
+ORDER BY (:pinned_first AND c.pinned) DESC, clipboard_fts.rank
+LIMIT coalesce(:limit, -1) OFFSET :offset
@@ -0,0 +1 @@
+INSERT OR REPLACE INTO encryption (id, salt, key_check) VALUES (1, ?, ?)
@@ -0,0 +1 @@
+UPDATE clipboard SET expires_at = ? WHERE id = ?
@@ -0,0 +1,4 @@
+UPDATE clipboard
+SET pinned = ?
+WHERE
+    id = (SELECT id FROM clipboard ORDER BY last_updated DESC, id DESC LIMIT 1 OFFSET ?)
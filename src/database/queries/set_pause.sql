@@ -0,0 +1 @@
+INSERT OR REPLACE INTO pause (id, until) VALUES (1, ?)
@@ -0,0 +1 @@
+UPDATE clipboard SET pinned = ? WHERE id = ?
@@ -1,3 +1,10 @@
 DELETE
 FROM clipboard
-WHERE id IN (SELECT id FROM clipboard ORDER BY last_updated ASC LIMIT ?)
+WHERE id IN (
+    SELECT id
+    FROM clipboard
+    WHERE (?1 OR NOT pinned)
+        AND (NOT ?2 OR id NOT IN (SELECT entry_id FROM clipboard_tag))
+    ORDER BY last_updated ASC, id ASC
+    LIMIT ?3
+)
@@ -0,0 +1 @@
+UPDATE clipboard SET hash = encrypt(?) WHERE id = ?
@@ -0,0 +1,3 @@
+UPDATE clipboard
+SET kind = ?, size = ?, width = ?, height = ?, preview = ?
+WHERE id = ?
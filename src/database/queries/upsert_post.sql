@@ -1,4 +1,8 @@
 INSERT
-INTO clipboard (content, last_updated)
-VALUES (?, ?)
-ON CONFLICT (content) DO UPDATE SET last_updated = excluded.last_updated
+INTO clipboard (content, codec, hash, last_updated, mime_type, kind, size, width, height, preview, expires_at)
+VALUES (encrypt(?), ?, encrypt(?), ?, ?, ?, ?, ?, ?, encrypt(?), ?)
+ON CONFLICT (hash) DO UPDATE SET
+    last_updated = max(last_updated, excluded.last_updated),
+    mime_type = iif(excluded.last_updated >= last_updated, excluded.mime_type, mime_type),
+    expires_at = iif(excluded.last_updated >= last_updated, excluded.expires_at, expires_at)
+RETURNING id
@@ -0,0 +1,12 @@
+INSERT INTO snippet (name, content, last_updated, mime_type, kind, size, width, height, preview)
+VALUES (:name, encrypt(:content), :last_updated, :mime_type, :kind, :size, :width, :height, encrypt(:preview))
+ON CONFLICT (name) DO UPDATE SET
+    content = excluded.content,
+    last_updated = excluded.last_updated,
+    mime_type = excluded.mime_type,
+    kind = excluded.kind,
+    size = excluded.size,
+    width = excluded.width,
+    height = excluded.height,
+    preview = excluded.preview
+WHERE :replace
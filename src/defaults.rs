@@ -1,4 +1,4 @@
-use dirs::{config_dir, data_local_dir};
+use dirs::{cache_dir, config_dir, data_local_dir};
 use std::{path::PathBuf, sync::LazyLock};
 
 pub static DB_PATH: LazyLock<PathBuf> = LazyLock::new(|| {
@@ -14,9 +14,25 @@ pub static CONFIG_PATH: LazyLock<PathBuf> = LazyLock::new(|| {
         .join("config.toml")
 });
 
+pub static THUMBNAILS_DIR: LazyLock<PathBuf> = LazyLock::new(|| {
+    cache_dir()
+        .expect("could not identify cache directory")
+        .join("clipvault")
+        .join("thumbs")
+});
+
+/// Time to wait for other processes to release their lock on the database.
+pub const BUSY_TIMEOUT: &str = "5s";
+
 pub const MAX_ENTRIES: usize = 1000;
 pub const MAX_ENTRY_AGE: &str = "14d";
 pub const MAX_ENTRY_LEN: usize = 5000000;
 pub const MIN_ENTRY_LEN: usize = 0;
+/// How long entries which look like secrets are kept.
+pub const SECRET_TTL: &str = "5m";
 
 pub const MAX_PREVIEW_WIDTH: usize = 100;
+/// Maximum number of characters of text stored for previews.
+pub const MAX_STORED_PREVIEW_LEN: usize = 1000;
+
+pub const THUMBNAIL_SIZE: u32 = 256;
@@ -1,6 +1,12 @@
+pub mod archive;
 pub mod cli;
+pub mod cliphist;
 pub mod commands;
+pub mod config;
 pub mod database;
 pub mod defaults;
 pub mod logging;
+pub mod rules;
+pub mod secrets;
+pub mod template;
 pub mod utils;
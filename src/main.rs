@@ -1,8 +1,10 @@
-use clap::Parser;
-
 use clipvault::{
     cli::{Cli, Commands},
     commands,
+    database::{
+        encryption::{read_secret, set_secret},
+        set_busy_timeout,
+    },
     logging::{init_logging, trace_err},
 };
 
@@ -20,15 +22,40 @@ fn main() -> Result<()> {
     .context("failed to parse arguments from argfile")
     .inspect_err(trace_err)?;
 
-    let args = Cli::parse_from(args);
+    let args = Cli::parse_with_config(args).inspect_err(trace_err)?;
     let path_db = args.database;
+    set_busy_timeout(args.busy_timeout.into());
+
+    if let Some(secret) = read_secret(args.key.key_file.as_deref(), args.key.passphrase.as_deref())
+        .inspect_err(trace_err)?
+    {
+        set_secret(secret);
+    }
 
     match args.command {
         Commands::List(args) => commands::list::execute(&path_db, args),
         Commands::Store(args) => commands::store::execute(&path_db, args),
+        Commands::Pause(args) => commands::pause::pause(&path_db, args),
+        Commands::Resume => commands::pause::resume(&path_db),
+        Commands::Status => commands::pause::status(&path_db),
         Commands::Get(args) => commands::get::execute(&path_db, args),
         Commands::Delete(args) => commands::delete::execute(&path_db, args),
-        Commands::Clear => commands::clear::execute(&path_db),
+        Commands::Clear(args) => commands::clear::execute(&path_db, args),
+        Commands::Pin(args) => commands::pin::execute(&path_db, args, true),
+        Commands::Unpin(args) => commands::pin::execute(&path_db, args, false),
+        Commands::Expire(args) => commands::expire::execute(&path_db, args),
+        Commands::Rules(command) => commands::rules::execute(command),
+        Commands::Tag(command) => commands::tag::execute(&path_db, command),
+        Commands::Snippet(command) => commands::snippet::execute(&path_db, command),
+        Commands::Search(args) => commands::search::execute(&path_db, args),
+        Commands::Export(args) => commands::export::execute(&path_db, args),
+        Commands::Import(args) => commands::import::execute(&path_db, args),
+        Commands::Merge(args) => commands::merge::execute(&path_db, args),
+        Commands::Vacuum => commands::vacuum::execute(&path_db),
+        Commands::Encrypt => commands::encryption::encrypt(&path_db, &args.key),
+        Commands::Decrypt => commands::encryption::decrypt(&path_db, &args.key),
+        Commands::Rekey(rekey) => commands::encryption::rekey(&path_db, &args.key, rekey),
+        Commands::Thumbnail(args) => commands::thumbnail::execute(&path_db, args),
     }
     .inspect_err(trace_err)
 }
@@ -0,0 +1,480 @@
+//! Rules from the config file which decide how entries are stored, beyond the ignore patterns.
+//!
+//! Each rule combines conditions, which must all match, with actions such as ignoring the entry,
+//! setting a TTL, tagging, pinning or transforming it. Rules are checked in order, and only the
+//! first matching rule is applied.
+
+use std::{borrow::Cow, cell::OnceCell, fmt::Display, str::FromStr};
+
+use jiff::civil::Time;
+use regex::Regex;
+use serde::{Deserialize, Deserializer};
+
+use crate::{
+    cli::parse_tag,
+    config::from_str,
+    database::data::{EntryKind, EntryMetadata},
+};
+
+/// A rule for storing entries, read from a `[[rule]]` table of the config file.
+///
+/// Conditions which are not set always match, so a rule without any conditions matches every
+/// entry.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Rule {
+    /// Shown in the debug logs and by `rules test`. Defaults to the position of the rule.
+    pub name: Option<String>,
+
+    /// Glob pattern for the MIME type, e.g. `image/*`.
+    pub mime_type: Option<String>,
+    pub kind: Option<EntryKind>,
+    /// Minimum size of the content in bytes.
+    pub min_size: Option<usize>,
+    /// Maximum size of the content in bytes.
+    pub max_size: Option<usize>,
+    /// Regex which must match the content. Never matches content which isn't UTF-8 text.
+    #[serde(deserialize_with = "from_str")]
+    pub pattern: Option<Regex>,
+    /// Value of `CLIPBOARD_STATE`, as set by wl-clipboard.
+    pub clipboard_state: Option<String>,
+    /// Glob pattern for the application the entry was copied from, given with `store --source`.
+    pub source: Option<String>,
+    /// Range of local times of day, e.g. `09:00-17:00`.
+    #[serde(deserialize_with = "from_str")]
+    pub time: Option<TimeRange>,
+
+    pub action: RuleAction,
+    /// Delete the entry once this much time has passed, as for `store --ttl`.
+    #[serde(deserialize_with = "from_str")]
+    pub ttl: Option<humantime::Duration>,
+    #[serde(deserialize_with = "tags")]
+    pub tags: Vec<String>,
+    pub pin: bool,
+    /// Changes to the text of the entry, applied in order before it is stored.
+    pub transform: Vec<Transform>,
+}
+
+/// What to do with entries which match a rule.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleAction {
+    #[default]
+    Store,
+    Ignore,
+}
+
+/// A change to the text of an entry. Content which isn't UTF-8 text is never changed.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub enum Transform {
+    /// Remove leading and trailing whitespace.
+    Trim,
+    /// Replace every match of the regex, where `$1` etc. refer to capture groups.
+    Replace {
+        #[serde(deserialize_with = "regex")]
+        pattern: Regex,
+        with: String,
+    },
+}
+
+/// A range of local times of day, given as `HH:MM-HH:MM`. The end is exclusive, and ranges which
+/// end before they start continue past midnight, e.g. `22:00-06:00`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeRange {
+    start: Time,
+    end: Time,
+}
+
+impl FromStr for TimeRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse = |t: &str| {
+            t.trim()
+                .parse::<Time>()
+                .map_err(|_| format!("invalid time of day: {t}"))
+        };
+        let (start, end) = s
+            .split_once('-')
+            .ok_or_else(|| format!("expected HH:MM-HH:MM, got: {s}"))?;
+
+        Ok(Self {
+            start: parse(start)?,
+            end: parse(end)?,
+        })
+    }
+}
+
+impl Display for TimeRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let format = |t: Time| t.strftime("%H:%M");
+        write!(f, "{}-{}", format(self.start), format(self.end))
+    }
+}
+
+impl TimeRange {
+    pub fn contains(&self, time: Time) -> bool {
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+}
+
+/// What is known about an entry when checking the rules.
+#[derive(Debug)]
+pub struct RuleInput<'a> {
+    pub content: &'a [u8],
+    pub mime_type: &'a str,
+    pub clipboard_state: Option<&'a str>,
+    pub source: Option<&'a str>,
+    /// Local time of day.
+    pub time: Time,
+    /// Only detected if a rule checks it, as images are decoded to do so.
+    kind: OnceCell<EntryKind>,
+}
+
+impl<'a> RuleInput<'a> {
+    pub fn new(
+        content: &'a [u8],
+        mime_type: &'a str,
+        clipboard_state: Option<&'a str>,
+        source: Option<&'a str>,
+        time: Time,
+    ) -> Self {
+        Self {
+            content,
+            mime_type,
+            clipboard_state,
+            source,
+            time,
+            kind: OnceCell::new(),
+        }
+    }
+
+    fn kind(&self) -> EntryKind {
+        *self
+            .kind
+            .get_or_init(|| EntryMetadata::from_content(self.content).kind)
+    }
+}
+
+impl Rule {
+    /// Name of the rule at the given index, for messages.
+    pub fn name(&self, index: usize) -> Cow<'_, str> {
+        match &self.name {
+            Some(name) => Cow::Borrowed(name),
+            None => Cow::Owned(format!("rule {}", index + 1)),
+        }
+    }
+
+    /// Whether every condition of the rule matches the input.
+    pub fn matches(&self, input: &RuleInput) -> bool {
+        let size = input.content.len();
+
+        self.mime_type
+            .as_deref()
+            .is_none_or(|pattern| glob_match(pattern, input.mime_type))
+            && self.min_size.is_none_or(|min| size >= min)
+            && self.max_size.is_none_or(|max| size <= max)
+            && self
+                .clipboard_state
+                .as_deref()
+                .is_none_or(|state| input.clipboard_state == Some(state))
+            && self.source.as_deref().is_none_or(|pattern| {
+                input
+                    .source
+                    .is_some_and(|source| glob_match(pattern, source))
+            })
+            && self.time.is_none_or(|range| range.contains(input.time))
+            && self.pattern.as_ref().is_none_or(|re| {
+                std::str::from_utf8(input.content).is_ok_and(|text| re.is_match(text))
+            })
+            && self.kind.is_none_or(|kind| kind == input.kind())
+    }
+
+    /// Apply the transforms of the rule to the content.
+    pub fn transform(&self, content: Vec<u8>) -> Vec<u8> {
+        if self.transform.is_empty() {
+            return content;
+        }
+        let mut text = match String::from_utf8(content) {
+            Ok(text) => text,
+            Err(e) => return e.into_bytes(),
+        };
+
+        for transform in &self.transform {
+            text = match transform {
+                Transform::Trim => text.trim().to_owned(),
+                Transform::Replace { pattern, with } => {
+                    pattern.replace_all(&text, with.as_str()).into_owned()
+                }
+            };
+        }
+
+        text.into_bytes()
+    }
+}
+
+/// Get the first rule which matches the input, with its index.
+pub fn first_match<'a>(rules: &'a [Rule], input: &RuleInput) -> Option<(usize, &'a Rule)> {
+    rules
+        .iter()
+        .enumerate()
+        .find(|(_, rule)| rule.matches(input))
+}
+
+/// Current local time of day, used to check the time ranges of rules.
+pub fn local_time() -> Time {
+    jiff::Zoned::now().time()
+}
+
+/// Whether the text matches the glob pattern, with the same syntax as SQLite's `GLOB` used by
+/// `list --mime`: `*` matches any characters, `?` matches a single character, and `[...]` matches
+/// a single character in the set, e.g. `[a-z]`, or not in it with `[^...]`. Matching is
+/// case-sensitive.
+pub(crate) fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let text = text.chars().collect::<Vec<_>>();
+
+    let (mut p, mut t) = (0, 0);
+    // Position after the last `*`, and the position in the text it currently matches up to
+    let mut star = None;
+
+    while t < text.len() {
+        if pattern.get(p) == Some(&'*') {
+            star = Some((p + 1, t));
+            p += 1;
+            continue;
+        }
+
+        match match_char(&pattern[p..], text[t]) {
+            Some(len) => {
+                p += len;
+                t += 1;
+            }
+            None => match star {
+                // Let the last `*` match one more character, and try again
+                Some((after, matched)) => {
+                    star = Some((after, matched + 1));
+                    p = after;
+                    t = matched + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// If the start of the glob pattern matches the character, get the length of the part of the
+/// pattern which matched.
+fn match_char(pattern: &[char], c: char) -> Option<usize> {
+    match *pattern.first()? {
+        '?' => Some(1),
+        '[' => match_set(&pattern[1..], c).map(|len| len + 1),
+        p => (p == c).then_some(1),
+    }
+}
+
+/// If the set at the start of the glob pattern, following the opening `[`, matches the character,
+/// get its length including the closing `]`. A set which is never closed doesn't match.
+///
+/// As for SQLite, a `]` at the start of the set and a `-` at either end are matched literally.
+fn match_set(set: &[char], c: char) -> Option<usize> {
+    let invert = set.first() == Some(&'^');
+    let mut i = usize::from(invert);
+    let mut seen = false;
+    if set.get(i) == Some(&']') {
+        seen = c == ']';
+        i += 1;
+    }
+
+    // Previous character in the set, which can start a range
+    let mut prior = None;
+    loop {
+        match *set.get(i)? {
+            ']' => break,
+            '-' if prior.is_some() && set.get(i + 1).is_some_and(|&end| end != ']') => {
+                let start = prior.take()?;
+                seen |= (start..=set[i + 1]).contains(&c);
+                i += 2;
+            }
+            x => {
+                seen |= x == c;
+                prior = Some(x);
+                i += 1;
+            }
+        }
+    }
+
+    (seen != invert).then_some(i + 1)
+}
+
+/// Deserialize a regex for a transform.
+fn regex<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Regex, D::Error> {
+    Regex::new(&String::deserialize(deserializer)?).map_err(serde::de::Error::custom)
+}
+
+/// Deserialize tags, which have the same restrictions as for `store --tag`.
+fn tags<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    Vec::<String>::deserialize(deserializer)?
+        .iter()
+        .map(|tag| parse_tag(tag).map_err(serde::de::Error::custom))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use jiff::civil::time;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn parse(s: &str) -> Vec<Rule> {
+        #[derive(Deserialize)]
+        struct Rules {
+            rule: Vec<Rule>,
+        }
+        toml::from_str::<Rules>(s).unwrap().rule
+    }
+
+    fn input<'a>(content: &'a str, mime_type: &'a str) -> RuleInput<'a> {
+        RuleInput::new(content.as_bytes(), mime_type, None, None, time(12, 0, 0, 0))
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("text/*", "text/plain"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("*fox*", "firefox-esr"));
+        assert!(glob_match("image/?ng", "image/png"));
+        assert!(glob_match("a*b*c", "aXbYbZc"));
+        assert!(!glob_match("text/*", "image/png"));
+        assert!(!glob_match("image/?ng", "image/jpeg"));
+        assert!(!glob_match("a*b", "aXbY"));
+
+        // Sets, as for SQLite's GLOB
+        assert!(glob_match("image/[pj]*", "image/png"));
+        assert!(glob_match("image/[a-z]?eg", "image/jpeg"));
+        assert!(glob_match("text/[^h]*", "text/plain"));
+        assert!(glob_match("[]x]", "]"));
+        assert!(glob_match("[a-]", "-"));
+        assert!(glob_match("*[0-9]", "audio/mp4"));
+        assert!(!glob_match("image/[pj]*", "image/gif"));
+        assert!(!glob_match("text/[^h]*", "text/html"));
+        assert!(!glob_match("[a-z]", "A"));
+        assert!(!glob_match("image/[png", "image/p"));
+        assert!(!glob_match("Text/*", "text/plain"));
+    }
+
+    #[test]
+    fn test_time_range() {
+        let day = "09:00-17:00".parse::<TimeRange>().unwrap();
+        assert!(day.contains(time(9, 0, 0, 0)));
+        assert!(day.contains(time(16, 59, 0, 0)));
+        assert!(!day.contains(time(17, 0, 0, 0)));
+        assert!(!day.contains(time(3, 0, 0, 0)));
+        assert_eq!(day.to_string(), "09:00-17:00");
+
+        let night = "22:00-06:00".parse::<TimeRange>().unwrap();
+        assert!(night.contains(time(23, 30, 0, 0)));
+        assert!(night.contains(time(5, 0, 0, 0)));
+        assert!(!night.contains(time(12, 0, 0, 0)));
+
+        assert!("09:00".parse::<TimeRange>().is_err());
+        assert!("9am-5pm".parse::<TimeRange>().is_err());
+    }
+
+    #[test]
+    fn test_first_match() {
+        let rules = parse(
+            r#"
+            [[rule]]
+            name = "codes"
+            pattern = '^\d{6}$'
+            ttl = "1m"
+
+            [[rule]]
+            mime-type = "text/*"
+            source = "*firefox*"
+            tags = ["web"]
+
+            [[rule]]
+            name = "night"
+            time = "22:00-06:00"
+            action = "ignore"
+
+            [[rule]]
+            name = "big text"
+            kind = "text"
+            min-size = 10
+            "#,
+        );
+
+        let name = |input: RuleInput| {
+            first_match(&rules, &input).map(|(i, rule)| rule.name(i).into_owned())
+        };
+
+        assert_eq!(
+            name(input("123456", "text/plain")).as_deref(),
+            Some("codes")
+        );
+        assert_eq!(name(input("1234567", "text/plain")), None);
+
+        let mut web = input("hello", "text/html");
+        web.source = Some("org.mozilla.firefox");
+        assert_eq!(name(web).as_deref(), Some("rule 2"));
+
+        let mut late = input("hello", "text/plain");
+        late.time = time(23, 0, 0, 0);
+        assert_eq!(name(late).as_deref(), Some("night"));
+
+        assert_eq!(
+            name(input("hello world", "text/plain")).as_deref(),
+            Some("big text")
+        );
+        let binary = RuleInput::new(
+            b"\x00\x01\x02\x03\x04\x05\x06\x07\x08\x09",
+            "application/octet-stream",
+            None,
+            None,
+            time(12, 0, 0, 0),
+        );
+        assert_eq!(name(binary), None);
+    }
+
+    #[test]
+    fn test_transform() {
+        let rules = parse(
+            r#"
+            [[rule]]
+            transform = [
+                "trim",
+                { replace = { pattern = '[?&]utm_[a-z]+=[^&]*', with = "" } },
+            ]
+            "#,
+        );
+
+        assert_eq!(
+            rules[0].transform(b"  https://example.com/?utm_source=x\n".to_vec()),
+            b"https://example.com/"
+        );
+        assert_eq!(rules[0].transform(vec![0xff, b' ']), vec![0xff, b' ']);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let parse = |s: &str| toml::from_str::<Rule>(s);
+
+        assert!(parse("tags = [\"a b\"]").is_err());
+        assert!(parse("time = \"9-5\"").is_err());
+        assert!(parse("action = \"delete\"").is_err());
+        assert!(parse("kind = \"video\"").is_err());
+        assert!(parse("patern = \"a\"").is_err());
+        assert!(parse("transform = [{ replace = { pattern = \"[\", with = \"\" } }]").is_err());
+    }
+}
@@ -0,0 +1,208 @@
+use std::str::FromStr;
+
+/// Value which can be inserted into a template, written as e.g. `{id}`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Placeholder {
+    /// ID of the entry.
+    Id,
+    /// Same preview as shown by the plain output format.
+    Preview,
+    /// MIME type of the entry.
+    Mime,
+    /// Kind of content, e.g. `text` or `image`.
+    Kind,
+    /// Human-readable size of the entry, e.g. `12kB`.
+    Size,
+    /// Width of image entries in pixels.
+    Width,
+    /// Height of image entries in pixels.
+    Height,
+    /// Human-readable time since the entry was last copied, e.g. `5m`.
+    Age,
+    /// Unix timestamp of when the entry was last copied.
+    LastUpdated,
+    /// `*` for pinned entries, otherwise empty.
+    Pinned,
+    /// Comma-separated tags of the entry.
+    Tags,
+    /// Path to a thumbnail of image entries.
+    Thumbnail,
+}
+
+impl FromStr for Placeholder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "id" => Self::Id,
+            "preview" => Self::Preview,
+            "mime" => Self::Mime,
+            "kind" => Self::Kind,
+            "size" => Self::Size,
+            "width" => Self::Width,
+            "height" => Self::Height,
+            "age" => Self::Age,
+            "last_updated" => Self::LastUpdated,
+            "pinned" => Self::Pinned,
+            "tags" => Self::Tags,
+            "thumbnail" => Self::Thumbnail,
+            _ => return Err(format!("unknown placeholder: {{{s}}}")),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Segment {
+    Literal(String),
+    Placeholder(Placeholder),
+}
+
+/// A parsed template for formatting each line of output, e.g. `{id}\t{preview}`.
+///
+/// Supports the escape sequences `\n`, `\t`, `\0`, `\\` and `\xHH` (an ASCII character given
+/// as two hex digits, e.g. `\x1f`), as well as `{{` and `}}` for literal braces.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template(Vec<Segment>);
+
+impl Template {
+    pub fn segments(&self) -> &[Segment] {
+        &self.0
+    }
+
+    /// Whether the template includes the given placeholder.
+    pub fn contains(&self, placeholder: Placeholder) -> bool {
+        self.0.contains(&Segment::Placeholder(placeholder))
+    }
+}
+
+impl FromStr for Template {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut segments = Vec::new();
+        let mut literal = String::new();
+        let mut chars = s.chars();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => literal.push(match chars.next() {
+                    Some('n') => '\n',
+                    Some('t') => '\t',
+                    Some('0') => '\0',
+                    Some('\\') => '\\',
+                    Some('x') => {
+                        let hex = chars.by_ref().take(2).collect::<String>();
+                        u8::from_str_radix(&hex, 16)
+                            .ok()
+                            .filter(|_| hex.len() == 2)
+                            .filter(u8::is_ascii)
+                            .map(char::from)
+                            .ok_or_else(|| format!("invalid escape sequence: \\x{hex}"))?
+                    }
+                    Some(other) => return Err(format!("invalid escape sequence: \\{other}")),
+                    None => return Err("template ends with an unescaped '\\'".into()),
+                }),
+                '{' if chars.as_str().starts_with('{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.as_str().starts_with('}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let (name, rest) = chars
+                        .as_str()
+                        .split_once('}')
+                        .ok_or("unclosed '{' in template - use '{{' for a literal brace")?;
+                    let placeholder = name.parse()?;
+                    chars = rest.chars();
+
+                    if !literal.is_empty() {
+                        segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                    }
+                    segments.push(Segment::Placeholder(placeholder));
+                }
+                '}' => {
+                    return Err("unmatched '}' in template - use '}}' for a literal brace".into());
+                }
+                c => literal.push(c),
+            }
+        }
+
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(literal));
+        }
+
+        Ok(Self(segments))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn lit(s: &str) -> Segment {
+        Segment::Literal(s.into())
+    }
+
+    fn ph(p: Placeholder) -> Segment {
+        Segment::Placeholder(p)
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Template::from_str("").unwrap().segments(), []);
+        assert_eq!(
+            Template::from_str("plain text").unwrap().segments(),
+            [lit("plain text")]
+        );
+        assert_eq!(
+            Template::from_str("{id}\\t{preview}").unwrap().segments(),
+            [ph(Placeholder::Id), lit("\t"), ph(Placeholder::Preview)]
+        );
+        assert_eq!(
+            Template::from_str("{width}x{height} {{{mime}}}")
+                .unwrap()
+                .segments(),
+            [
+                ph(Placeholder::Width),
+                lit("x"),
+                ph(Placeholder::Height),
+                lit(" {"),
+                ph(Placeholder::Mime),
+                lit("}"),
+            ]
+        );
+        assert_eq!(
+            Template::from_str("{preview}\\0icon\\x1f{id}\\n")
+                .unwrap()
+                .segments(),
+            [
+                ph(Placeholder::Preview),
+                lit("\0icon\x1f"),
+                ph(Placeholder::Id),
+                lit("\n"),
+            ]
+        );
+        assert_eq!(
+            Template::from_str("\\\\{age}").unwrap().segments(),
+            [lit("\\"), ph(Placeholder::Age)]
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(Template::from_str("{unknown}").is_err());
+        assert!(Template::from_str("{id").is_err());
+        assert!(Template::from_str("id}").is_err());
+        assert!(Template::from_str("{}").is_err());
+        assert!(Template::from_str("\\").is_err());
+        assert!(Template::from_str("\\q").is_err());
+        assert!(Template::from_str("\\x1").is_err());
+        assert!(Template::from_str("\\xzz").is_err());
+        assert!(Template::from_str("\\xff").is_err());
+    }
+}
@@ -3,8 +3,16 @@ use std::{
     time::{SystemTime, UNIX_EPOCH},
 };
 
+use content_inspector::ContentType;
+use mime_sniffer::MimeTypeSniffer;
 use unicode_segmentation::UnicodeSegmentation;
 
+/// Prefixes (lowercase) which identify text as HTML, e.g. when copied from a browser.
+const HTML_PREFIXES: &[&str] = &["<!doctype html", "<html", "<meta http-equiv="];
+
+/// MIME type used for binary data which could not be identified.
+pub const UNKNOWN_MIME_TYPE: &str = "application/octet-stream";
+
 /// Returns the given number of bytes as a human-readable string representation.
 pub fn human_bytes(mut bytes: usize) -> String {
     let unit = if bytes < 1_000 {
@@ -23,6 +31,24 @@ pub fn human_bytes(mut bytes: usize) -> String {
     format!("{bytes}{unit}")
 }
 
+/// Returns the given number of seconds as a short human-readable string, using only the largest
+/// whole unit, e.g. `42s`, `5m` or `3d`.
+pub fn human_age(secs: u64) -> String {
+    let (value, unit) = if secs < 60 {
+        (secs, "s")
+    } else if secs < 60 * 60 {
+        (secs / 60, "m")
+    } else if secs < 60 * 60 * 24 {
+        (secs / (60 * 60), "h")
+    } else if secs < 60 * 60 * 24 * 7 {
+        (secs / (60 * 60 * 24), "d")
+    } else {
+        (secs / (60 * 60 * 24 * 7), "w")
+    };
+
+    format!("{value}{unit}")
+}
+
 /// Truncates a string to the given number of characters.
 pub fn truncate(s: &str, max_graphemes: usize) -> Cow<'_, str> {
     let graphemes = s.graphemes(true).collect::<Vec<_>>();
@@ -39,6 +65,32 @@ pub fn truncate(s: &str, max_graphemes: usize) -> Cow<'_, str> {
     Cow::Owned(format!("{}…", graphemes[..max].join("")))
 }
 
+/// Best guess at the MIME type of the given data, e.g. `image/png` or `text/plain`.
+pub fn detect_mime_type(data: &[u8]) -> String {
+    if let Ok(format) = image::guess_format(data) {
+        return format.to_mime_type().into();
+    }
+
+    if let Some(mime_type) = data.sniff_mime_type() {
+        return mime_type.into();
+    }
+
+    match content_inspector::inspect(data) {
+        ContentType::UTF_8 | ContentType::UTF_8_BOM => {
+            let start = String::from_utf8_lossy(&data[..data.len().min(32)])
+                .trim_start()
+                .to_lowercase();
+
+            if HTML_PREFIXES.iter().any(|p| start.starts_with(p)) {
+                "text/html".into()
+            } else {
+                "text/plain".into()
+            }
+        }
+        _ => UNKNOWN_MIME_TYPE.into(),
+    }
+}
+
 /// Current Unix timestamp in seconds - based on system time.
 pub fn now() -> u64 {
     SystemTime::now()
@@ -116,6 +168,41 @@ mod test {
         assert_eq!(human_bytes(2_000_000_000), String::from("2GB"));
     }
 
+    #[test]
+    fn test_human_age() {
+        assert_eq!(human_age(0), String::from("0s"));
+        assert_eq!(human_age(59), String::from("59s"));
+        assert_eq!(human_age(60), String::from("1m"));
+        assert_eq!(human_age(3_599), String::from("59m"));
+        assert_eq!(human_age(3_600), String::from("1h"));
+        assert_eq!(human_age(86_399), String::from("23h"));
+        assert_eq!(human_age(86_400), String::from("1d"));
+        assert_eq!(human_age(604_799), String::from("6d"));
+        assert_eq!(human_age(604_800), String::from("1w"));
+        assert_eq!(human_age(6_048_000), String::from("10w"));
+    }
+
+    #[test]
+    fn test_detect_mime_type() {
+        assert_eq!(detect_mime_type(b"hello world"), "text/plain");
+        assert_eq!(detect_mime_type("😀 ᚅ ᚆ ᚇ".as_bytes()), "text/plain");
+        assert_eq!(
+            detect_mime_type(b"  <html><body></body></html>"),
+            "text/html"
+        );
+        assert_eq!(
+            detect_mime_type(b"<meta http-equiv=\"content-type\"><img src=\"\">"),
+            "text/html"
+        );
+        assert_eq!(detect_mime_type(b"%PDF-1.4 ..."), "application/pdf");
+        assert_eq!(detect_mime_type(b"\x89PNG\r\n\x1a\n"), "image/png");
+        assert_eq!(detect_mime_type(b"GIF89a"), "image/gif");
+        assert_eq!(
+            detect_mime_type(b"\x00\x01\x02\xff\xfe"),
+            "application/octet-stream"
+        );
+    }
+
     #[test]
     fn test_ignore_broken_pipe() {
         use std::io::{Error, ErrorKind};